@@ -0,0 +1,156 @@
+-- lua/context-groups/branches.lua
+-- Current git branch of projects, for personal groups scoped to a branch
+
+local config = require("context-groups.config")
+local utils = require("context-groups.utils")
+
+local M = {}
+
+local uv = vim.uv or vim.loop
+
+-- Git state per project root; branch is nil for a detached HEAD
+---@type table<string, {git_dir: string|nil, branch: string|nil, default: string|nil, watcher: uv_fs_event_t|nil}>
+local states = {}
+
+---Trigger configured callback function
+local function notify_context_change()
+  local cfg = config.get()
+  if cfg.on_context_change then
+    cfg.on_context_change()
+  end
+end
+
+---Read the branch checked out in a git directory
+---@param git_dir string Absolute git directory, the worktree's own one in linked worktrees
+---@return string|nil branch Nil for a detached HEAD
+local function read_head(git_dir)
+  local content = utils.read_file_content(git_dir .. "/HEAD")
+  return content and content:match("^ref: refs/heads/(%S+)") or nil
+end
+
+---Detect the default branch of a repository
+---@param root string Project root directory
+---@return string|nil branch
+local function detect_default_branch(root)
+  local configured = config.get().default_branch
+  if configured then
+    return configured
+  end
+
+  local ref = vim.fn.systemlist({ "git", "-C", root, "symbolic-ref", "--quiet", "--short", "refs/remotes/origin/HEAD" })
+  if vim.v.shell_error == 0 and ref[1] then
+    return (ref[1]:gsub("^[^/]+/", ""))
+  end
+
+  for _, name in ipairs({ "main", "master" }) do
+    vim.fn.systemlist({ "git", "-C", root, "rev-parse", "--verify", "--quiet", "refs/heads/" .. name })
+    if vim.v.shell_error == 0 then
+      return name
+    end
+  end
+  return nil
+end
+
+---Watch HEAD and switch the groups of a project when another branch is checked out
+---@param root string Project root directory
+---@param state table Git state of the project
+local function watch_head(root, state)
+  local watcher = uv.new_fs_event()
+  if not watcher then
+    return
+  end
+
+  -- Watch the directory: git replaces HEAD through a lock file
+  local switch_pending = false
+  local ok = watcher:start(state.git_dir, {}, function(err, filename)
+    if err or filename ~= "HEAD" or switch_pending then
+      return
+    end
+    switch_pending = true
+    vim.schedule(function()
+      switch_pending = false
+      local branch = read_head(state.git_dir)
+      if branch == state.branch then
+        return
+      end
+
+      state.branch = branch
+      if config.get().branch_groups then
+        local name = M.get_branch(root) or state.default or "default branch"
+        vim.notify(string.format("Context groups switched to %s", name), vim.log.levels.INFO)
+        notify_context_change()
+      end
+    end)
+  end)
+
+  if ok then
+    state.watcher = watcher
+  else
+    watcher:close()
+  end
+end
+
+---Get the git state of a project, reading it on first use
+---@param root string Canonical project root
+---@return {git_dir: string|nil, branch: string|nil, default: string|nil} state
+local function get_state(root)
+  if not states[root] then
+    local state = {}
+    local git_dir = vim.fn.systemlist({ "git", "-C", root, "rev-parse", "--absolute-git-dir" })[1]
+    if vim.v.shell_error == 0 and git_dir then
+      state.git_dir = git_dir
+      state.branch = read_head(git_dir)
+      state.default = detect_default_branch(root)
+      watch_head(root, state)
+    end
+    states[root] = state
+  end
+  return states[root]
+end
+
+---Get the branch whose personal groups a project uses
+---@param root string Canonical project root
+---@return string|nil branch Nil when the default groups apply: branch groups disabled, no git repository,
+---detached HEAD or the default branch checked out
+function M.get_branch(root)
+  if not config.get().branch_groups then
+    return nil
+  end
+
+  local state = get_state(root)
+  if not state.branch or state.branch == state.default then
+    return nil
+  end
+  return state.branch
+end
+
+---Get the default branch of a project, whose groups other branches fall back to
+---@param root string Canonical project root
+---@return string|nil branch
+function M.get_default_branch(root)
+  return get_state(root).default
+end
+
+---Stop watching HEAD and forget the git state of a project, or of every project
+---@param root? string Canonical project root
+function M.close(root)
+  for state_root, state in pairs(states) do
+    if not root or state_root == root then
+      if state.watcher then
+        state.watcher:stop()
+        state.watcher:close()
+      end
+      states[state_root] = nil
+    end
+  end
+end
+
+---List the local branches of a project
+---@param root string Canonical project root
+---@return string[] branches
+function M.list(root)
+  local branches = vim.fn.systemlist({ "git", "-C", root, "branch", "--format=%(refname:short)" })
+  return vim.v.shell_error == 0 and branches or {}
+end
+
+return M
@@ -0,0 +1,213 @@
+-- lua/context-groups/bundles.lua
+-- Portable bundle files holding context group definitions with root-relative paths
+
+local entries = require("context-groups.entries")
+local storage = require("context-groups.storage")
+local utils = require("context-groups.utils")
+
+local M = {}
+
+-- Current bundle format version; bundles hold groups in the storage format of the same version
+local BUNDLE_VERSION = 3
+
+---@class Bundle
+---@field version integer Bundle format version
+---@field groups table<string, (string|table)[]> Groups by storage key: root-relative file paths or named groups
+
+---Get the format of a bundle file from its extension
+---@param path string Bundle file path
+---@return "json"|"yaml" format
+local function get_format(path)
+  local extension = vim.fn.fnamemodify(path, ":e"):lower()
+  return (extension == "yaml" or extension == "yml") and "yaml" or "json"
+end
+
+---Encode a bundle as YAML
+---Strings are written as double-quoted scalars, which JSON string escapes are valid for
+---@param bundle Bundle Bundle
+---@return string yaml
+local function encode_yaml(bundle)
+  local lines = { "version: " .. bundle.version, "groups:" }
+  local keys = vim.tbl_keys(bundle.groups)
+  table.sort(keys)
+
+  for _, key in ipairs(keys) do
+    local items = bundle.groups[key]
+    table.insert(lines, string.format("  %s:%s", vim.fn.json_encode(key), #items == 0 and " []" or ""))
+    for _, item in ipairs(items) do
+      if type(item) == "string" then
+        table.insert(lines, "    - " .. vim.fn.json_encode(item))
+      else
+        table.insert(lines, "    - entry: " .. vim.fn.json_encode(item.entry))
+        local fields = vim.tbl_keys(item)
+        table.sort(fields)
+        for _, field in ipairs(fields) do
+          if field ~= "entry" then
+            table.insert(lines, string.format("      %s: %s", field, vim.fn.json_encode(item[field])))
+          end
+        end
+      end
+    end
+  end
+
+  return table.concat(lines, "\n") .. "\n"
+end
+
+---Decode a YAML scalar: quoted strings, numbers, booleans or plain strings
+---@param text string Scalar text
+---@return any value
+local function decode_scalar(text)
+  text = vim.trim(text)
+  if text:match('^".*"$') then
+    return vim.fn.json_decode(text)
+  end
+  if text:match("^'.*'$") then
+    return (text:sub(2, -2):gsub("''", "'"))
+  end
+  if text == "true" or text == "false" then
+    return text == "true"
+  end
+  return tonumber(text) or text
+end
+
+---Decode YAML in the form written by `encode_yaml`
+---Only the mappings and lists of a bundle are understood; comments and blank lines are ignored
+---@param content string YAML content
+---@return Bundle|nil bundle, string|nil err
+local function decode_yaml(content)
+  local bundle = { groups = {} }
+  local group, item
+
+  for line_nr, line in ipairs(vim.split(content, "\n")) do
+    local indent, text = line:match("^( *)(.-)%s*$")
+    local ok, err = pcall(function()
+      if text == "" or text:match("^#") then
+        return
+      end
+
+      if #indent == 0 then
+        local field, value = text:match("^(%w+):%s*(.-)$")
+        if field == "version" then
+          bundle.version = decode_scalar(value)
+        elseif field ~= "groups" then
+          error("unexpected line")
+        end
+      elseif #indent == 2 then
+        local key, rest = text:match('^(".-"):%s*(.-)$')
+        if not key then
+          key, rest = text:match("^([^:]+):%s*(.-)$")
+        end
+        if not key or (rest ~= "" and rest ~= "[]") then
+          error("expected a group key")
+        end
+        group = {}
+        bundle.groups[decode_scalar(key)] = group
+      elseif #indent == 4 and group and text:match("^%- ") then
+        local value = text:sub(3)
+        local entry = value:match("^entry:%s*(.+)$")
+        item = entry and { entry = decode_scalar(entry) } or nil
+        table.insert(group, item or decode_scalar(value))
+      elseif #indent == 6 and item then
+        local field, value = text:match("^(%w+):%s*(.+)$")
+        if not field then
+          error("expected an entry field")
+        end
+        item[field] = decode_scalar(value)
+      else
+        error("unexpected indentation")
+      end
+    end)
+
+    if not ok then
+      return nil, string.format("line %d: %s", line_nr, (tostring(err):gsub("^.-:%d+: ", "")))
+    end
+  end
+
+  return bundle
+end
+
+---Check whether a key, file or rule could point outside the project: absolute or with a `..` segment
+---Stored paths are canonical, so a relative path never needs `..` to stay within the root
+---@param path string Key, file of an entry or rule without its marker
+---@return boolean
+local function is_outside_root(path)
+  path = path:gsub("^!", "")
+  return vim.startswith(path, "/") or ("/" .. path .. "/"):find("/%.%./") ~= nil
+end
+
+---Check the groups of a decoded bundle
+---@param bundle any Decoded bundle
+---@return string|nil err Nil when the bundle is valid
+local function validate(bundle)
+  if type(bundle) ~= "table" or type(bundle.groups) ~= "table" then
+    return "missing groups"
+  end
+  if next(bundle.groups) ~= nil and utils.is_list(bundle.groups) then
+    return "missing groups"
+  end
+  if type(bundle.version) ~= "number" or bundle.version < 1 then
+    return "missing or invalid version"
+  end
+  if bundle.version > BUNDLE_VERSION then
+    return "written by a newer version of the plugin"
+  end
+
+  for key, items in pairs(bundle.groups) do
+    if type(key) ~= "string" or is_outside_root(key) then
+      return string.format("%s: keys must be root-relative paths or named groups", tostring(key))
+    end
+    if type(items) ~= "table" or (next(items) ~= nil and not utils.is_list(items)) then
+      return string.format("%s: group is not a list", key)
+    end
+    for _, item in ipairs(items) do
+      local value = type(item) == "table" and item.entry or item
+      if type(value) ~= "string" or value == "" then
+        return string.format("%s: invalid entry %s", key, vim.inspect(item))
+      end
+      local rule = vim.startswith(value, storage.RULE_PREFIX) and value:sub(#storage.RULE_PREFIX + 1)
+      if is_outside_root(rule or entries.parse(value).file) then
+        return string.format("%s: entry %s is not root-relative", key, value)
+      end
+    end
+  end
+  return nil
+end
+
+---Write groups to a bundle file, as YAML for `.yaml`/`.yml` files and JSON otherwise
+---@param path string Bundle file path
+---@param groups table<string, (string|table)[]> Groups by storage key
+---@return boolean success
+function M.write(path, groups)
+  local bundle = { version = BUNDLE_VERSION, groups = groups }
+  local content = get_format(path) == "yaml" and encode_yaml(bundle) or utils.json_encode_pretty(bundle)
+  return utils.write_file_content(path, content)
+end
+
+---Read and check a bundle file
+---@param path string Bundle file path
+---@return Bundle|nil bundle, string|nil err
+function M.read(path)
+  local content = utils.read_file_content(path)
+  if not content then
+    return nil, "cannot read " .. path
+  end
+
+  local bundle, err
+  if get_format(path) == "yaml" then
+    bundle, err = decode_yaml(content)
+  else
+    local ok, decoded = pcall(vim.fn.json_decode, content)
+    bundle, err = ok and decoded or nil, not ok and "invalid JSON" or nil
+  end
+  if not bundle then
+    return nil, err
+  end
+
+  err = validate(bundle)
+  if err then
+    return nil, err
+  end
+  return (storage.upgrade(bundle))
+end
+
+return M
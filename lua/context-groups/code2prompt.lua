@@ -6,12 +6,25 @@ local utils = require("context-groups.utils")
 
 local M = {}
 
+-- Collect the notes and priority of a context entry by relative path
+---@param meta table<string, {notes: string[], priority: number}> Metadata by relative path, extended in place
+---@param rel_path string Relative path
+---@param entry {note: string|nil, priority: number|nil} Context entry of the file
+local function add_entry_meta(meta, rel_path, entry)
+  meta[rel_path] = meta[rel_path] or { notes = {}, priority = entry.priority or 0 }
+  meta[rel_path].priority = math.max(meta[rel_path].priority, entry.priority or 0)
+  if entry.note and not vim.tbl_contains(meta[rel_path].notes, entry.note) then
+    table.insert(meta[rel_path].notes, entry.note)
+  end
+end
+
 -- Get all open buffer file paths
----@return string[] file_paths, string root_path
+---@return string[] file_paths, string root_path, table<string, {notes: string[], priority: number}> meta
 local function get_open_buffer_files()
   local root = core.find_root(vim.fn.expand("%:p"))
   local files = {}
   local seen = {}
+  local meta = {}
 
   -- Get currently open files
   for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
@@ -23,21 +36,24 @@ local function get_open_buffer_files()
           path = path:sub(#root + 2) -- +2 to remove the trailing slash
           seen[path] = true
           table.insert(files, path)
+          -- Open buffers are kept over any context file when the size budget is exceeded
+          add_entry_meta(meta, path, { priority = math.huge })
 
           -- Get context group files for this buffer
-          local context_files = core.get_context_files(bufnr, { relative = true })
-          for _, context_file in ipairs(context_files) do
+          for _, entry in ipairs(core.get_context_entries(bufnr)) do
+            local context_file = core.get_relative_path(entry.path)
             if not seen[context_file] then
               seen[context_file] = true
               table.insert(files, context_file)
             end
+            add_entry_meta(meta, context_file, entry)
           end
         end
       end
     end
   end
 
-  return files, root
+  return files, root, meta
 end
 
 -- Format and copy the contents of current buffer to clipboard
@@ -87,10 +103,39 @@ function M.generate_current_buffer_prompt()
   return true
 end
 
+-- Get file paths of a named context group
+---@param group string Named group
+---@return string[] file_paths, string root_path, table<string, {notes: string[], priority: number}> meta
+local function get_group_files(group)
+  local root = core.get_current_root() or core.find_root(vim.fn.getcwd() .. "/")
+  local files = {}
+  local seen = {}
+  local meta = {}
+
+  for _, entry in ipairs(core.get_context_entries(nil, { group = group })) do
+    if vim.startswith(entry.path, root .. "/") then
+      local rel_path = entry.path:sub(#root + 2)
+      if not seen[rel_path] then
+        seen[rel_path] = true
+        table.insert(files, rel_path)
+      end
+      add_entry_meta(meta, rel_path, entry)
+    end
+  end
+
+  return files, root, meta
+end
+
 -- Format and copy the contents of open buffer files to clipboard
+---@param opts? {group: string} Options {group: Copy this named group instead of the open buffers}
 ---@return boolean success
-function M.generate_prompt()
-  local buffer_files, project_root = get_open_buffer_files()
+function M.generate_prompt(opts)
+  local buffer_files, project_root, meta
+  if opts and opts.group then
+    buffer_files, project_root, meta = get_group_files(opts.group)
+  else
+    buffer_files, project_root, meta = get_open_buffer_files()
+  end
 
   if #buffer_files == 0 then
     vim.notify("No valid open buffer files found", vim.log.levels.ERROR)
@@ -120,25 +165,41 @@ function M.generate_prompt()
   table.insert(output, string.format("Here are some files I've selected from the project %s.", project_name))
   table.insert(output, "They are as follows:")
 
-  -- Add file contents
+  -- Read files in group order, then drop low-priority files beyond the size budget
+  local files = {}
   for _, rel_path in ipairs(buffer_files) do
     local abs_path = project_root .. "/" .. rel_path
     local content = utils.read_file_content(abs_path)
-
     if content then
-      table.insert(output, "```")
-      table.insert(output, abs_path)
-      table.insert(output, content)
-      table.insert(output, "```")
-      table.insert(output, "") -- Add empty line between files
+      local file_meta = meta[rel_path] or { notes = {} }
+      table.insert(files, {
+        path = abs_path,
+        content = content,
+        notes = file_meta.notes,
+        priority = file_meta.priority,
+      })
     end
   end
+  files = core.apply_size_budget(files)
+
+  -- Add file contents
+  for _, file in ipairs(files) do
+    -- Notes say why a file matters
+    for _, note in ipairs(file.notes) do
+      table.insert(output, "Note: " .. note)
+    end
+    table.insert(output, "```")
+    table.insert(output, file.path)
+    table.insert(output, file.content)
+    table.insert(output, "```")
+    table.insert(output, "") -- Add empty line between files
+  end
 
   -- Combine all text and copy to clipboard
   local final_output = table.concat(output, "\n")
   vim.fn.setreg("+", final_output)
 
-  vim.notify(string.format("Contents of %d files copied to clipboard", #buffer_files), vim.log.levels.INFO)
+  vim.notify(string.format("Contents of %d files copied to clipboard", #files), vim.log.levels.INFO)
   return true
 end
 
@@ -16,15 +16,35 @@
 ---@field show_external boolean Show external dependencies
 ---@field ignore_patterns string[] Patterns to ignore when importing
 
+---@class ImportGraphConfig
+---@field depth integer Import hops followed when adding dependencies
+---@field max_files integer Maximum number of dependencies collected
+
+---@class PairingConfig
+---@field enabled boolean Infer test/implementation pairs
+---@field include boolean Include the files paired with a buffer in its context group
+---@field test_dirs string[] Directories that mirror the source tree with tests
+---@field rules table<string, {test: string, impl: string}[]> File name templates per filetype, see PairingRule
+
 ---@class ContextGroupsConfig
 ---@field keymaps ContextGroupsKeymaps Key mappings configuration
 ---@field storage_path? string Path to store plugin data
+---@field shared_file? string|false Project-local file holding team-shared groups (false to disable)
+---@field default_scope? "personal"|"shared" Scope used when adding files without an explicit scope
+---@field always_include_group? string|false Named group included in every context group of a project
+---@field size_budget? integer|false Maximum bytes of file content in an export, low-priority files are dropped first
+---@field history_size? integer Number of context group edits kept for undo per project
+---@field branch_groups? boolean Keep separate personal groups per git branch, falling back to the default branch's
+---@field default_branch? string Branch whose groups other branches fall back to (detected when unset)
+---@field gc_on_setup? boolean Prune stale keys and entries from personal storage of every project on setup
 ---@field import_prefs ImportPreferences Import preferences
+---@field import_graph? ImportGraphConfig Dependency closure of imports
+---@field pairing? PairingConfig Test/implementation pairing
 ---@field project_markers string[] Markers to identify project root
 ---@field max_preview_lines? number Maximum lines to show in preview
 ---@field telescope_theme? table Custom telescope theme
 ---@field on_context_change? function Called when context group changes
----@field language_config table<string, table> Language specific configurations
+---@field language_config table<string, table> Language specific configurations, `resolve = false` turns off a built-in resolver
 ---@field export table<string, any> Export configuration
 
 local M = {}
@@ -45,11 +65,56 @@ local DEFAULT_CONFIG = {
     buffer_paths = "<leader>cp",
   },
   storage_path = vim.fn.stdpath("data") .. "/context-groups",
+  shared_file = ".context-groups.json",
+  default_scope = "personal",
+  always_include_group = "always",
+  size_budget = false,
+  history_size = 100,
+  branch_groups = false,
+  default_branch = nil,
+  gc_on_setup = false,
+  pairing = {
+    enabled = true,
+    include = false,
+    test_dirs = { "tests", "test", "__tests__", "spec" },
+    rules = {
+      go = { { test = "{name}_test.go", impl = "{name}.go" } },
+      python = {
+        { test = "test_{name}.py", impl = "{name}.py" },
+        { test = "{name}_test.py", impl = "{name}.py" },
+      },
+      javascript = {
+        { test = "{name}.test.{ext}", impl = "{name}.{ext}" },
+        { test = "{name}.spec.{ext}", impl = "{name}.{ext}" },
+      },
+      javascriptreact = {
+        { test = "{name}.test.{ext}", impl = "{name}.{ext}" },
+        { test = "{name}.spec.{ext}", impl = "{name}.{ext}" },
+      },
+      typescript = {
+        { test = "{name}.test.{ext}", impl = "{name}.{ext}" },
+        { test = "{name}.spec.{ext}", impl = "{name}.{ext}" },
+      },
+      typescriptreact = {
+        { test = "{name}.test.{ext}", impl = "{name}.{ext}" },
+        { test = "{name}.spec.{ext}", impl = "{name}.{ext}" },
+      },
+      lua = { { test = "{name}_spec.lua", impl = "{name}.lua" } },
+      ruby = { { test = "{name}_spec.rb", impl = "{name}.rb" } },
+      java = { { test = "{name}Test.java", impl = "{name}.java" } },
+      c = { { test = "test_{name}.c", impl = "{name}.c" } },
+      cpp = { { test = "{name}_test.cpp", impl = "{name}.cpp" } },
+    },
+  },
   import_prefs = {
     show_stdlib = false,
     show_external = false,
     ignore_patterns = {},
   },
+  import_graph = {
+    depth = 2,
+    max_files = 200,
+  },
   project_markers = {
     ".git",
     ".svn",
@@ -63,12 +128,12 @@ local DEFAULT_CONFIG = {
   on_context_change = nil,
   language_config = {
     go = {
-      resolve_strategy = "gopls",
+      resolve = true,
       stdlib_path = vim.fn.systemlist("go env GOROOT")[1],
       external_path = vim.fn.systemlist("go env GOPATH")[1],
     },
     python = {
-      resolve_strategy = "jedi",
+      resolve = true,
       venv_detection = true,
       follow_imports = true,
     },
@@ -104,6 +169,20 @@ function M.setup(user_config)
   -- Deep merge user config with defaults
   config = vim.tbl_deep_extend("force", DEFAULT_CONFIG, user_config or {})
 
+  for lang, lang_config in pairs(config.language_config) do
+    if type(lang_config) == "table" and lang_config.resolve_strategy ~= nil then
+      vim.notify(
+        string.format(
+          "context-groups: language_config.%s.resolve_strategy is not used; set resolve = false to resolve %s imports "
+            .. "with the generic rules",
+          lang,
+          lang
+        ),
+        vim.log.levels.WARN
+      )
+    end
+  end
+
   -- Ensure storage path exists
   vim.fn.mkdir(config.storage_path, "p")
 end
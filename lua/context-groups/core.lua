@@ -1,7 +1,13 @@
 -- lua/context-groups/core.lua
 -- Core context management functionality
 
+local bundles = require("context-groups.bundles")
 local config = require("context-groups.config")
+local entries = require("context-groups.entries")
+local graph = require("context-groups.graph")
+local groups = require("context-groups.groups")
+local history = require("context-groups.history")
+local pairing = require("context-groups.pairing")
 local project = require("context-groups.project")
 local storage = require("context-groups.storage")
 local utils = require("context-groups.utils")
@@ -47,219 +53,1852 @@ local function get_current_filepath(bufnr)
   return nil
 end
 
+---Trigger configured callback function
+local function notify_context_change()
+  local cfg = config.get()
+  if cfg.on_context_change then
+    cfg.on_context_change()
+  end
+end
+
+---Resolve the storage scope to use
+---@param scope? "personal"|"shared" Requested scope
+---@return "personal"|"shared" scope
+local function resolve_scope(scope)
+  return scope or config.get().default_scope or "personal"
+end
+
+---Get storage for a project scope
+---@param root string Project root directory
+---@param scope "personal"|"shared" Storage scope
+---@return Storage|nil store Nil when the scope is unavailable
+local function get_scope_storage(root, scope)
+  if scope == "shared" then
+    return storage.get_shared_storage(root)
+  end
+  return storage.get_storage(root)
+end
+
+---Get the canonical project root for a path
+---@param path string File path
+---@return string root Canonical project root
+local function get_project_root(path)
+  -- Search from inside directories so their own markers are found
+  if vim.fn.isdirectory(path) == 1 then
+    path = path .. "/"
+  end
+  return utils.canonical_path(M.find_root(path))
+end
+
+---Get the canonical project root for a buffer
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return string|nil root Canonical project root
+function M.get_current_root(bufnr)
+  local file_path = get_current_filepath(bufnr)
+  return file_path and get_project_root(file_path) or nil
+end
+
+---Get the name of the named group active for a buffer's project
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return string|nil name
+function M.get_active_group(bufnr)
+  local root = M.get_current_root(bufnr)
+  return root and groups.get_active(root) or nil
+end
+
+---Resolve the group a buffer works with
+---An explicit or active named group takes precedence over the buffer's own group
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@param group? string Named group to use instead of the buffer's group
+---@return {root: string, path: string|nil, group: string|nil}|nil target Nil when no group applies
+local function resolve_target(bufnr, group)
+  local file_path = get_current_filepath(bufnr)
+  if not file_path then
+    return nil
+  end
+
+  local root = get_project_root(file_path)
+  group = group or groups.get_active(root)
+  if group then
+    return { root = root, group = group }
+  end
+
+  -- Without a file open there is no per-file group to use
+  if vim.fn.filereadable(file_path) ~= 1 then
+    return nil
+  end
+
+  return { root = root, path = file_path }
+end
+
+---Check whether a buffer works with a group: an explicit or active named group, or the group of its file
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@param opts? {group: string} Options {group: Named group to use instead of the buffer's group}
+---@return boolean
+function M.has_context_group(bufnr, opts)
+  return resolve_target(bufnr, opts and opts.group) ~= nil
+end
+
+---Convert a path to the form stored in a group
+---Paths are stored canonical and relative to the project root so groups survive clones and moves
+---@param path string File path
+---@param root string Canonical project root
+---@param scope "personal"|"shared" Storage scope
+---@return string|nil stored Nil when the path cannot be stored in the scope
+local function to_stored_path(path, root, scope)
+  path = utils.canonical_path(path)
+  if path == root then
+    return "."
+  end
+  if vim.startswith(path, root .. "/") then
+    return path:sub(#root + 2)
+  end
+
+  -- Files outside the project can only be kept in personal groups
+  if scope == "shared" then
+    return nil
+  end
+  return path
+end
+
+---Get the storage key of a target's group
+---@param target {root: string, path: string|nil, group: string|nil} Resolved target
+---@param scope "personal"|"shared" Storage scope
+---@return string|nil key Nil when the group cannot be stored in the scope
+local function get_group_key(target, scope)
+  if target.group then
+    return groups.key(target.group)
+  end
+  return to_stored_path(target.path, target.root, scope)
+end
+
+---Convert a stored path back to an absolute path
+---@param stored string Stored path
+---@param root string Canonical project root
+---@return string path Absolute path
+local function from_stored_path(stored, root)
+  if vim.startswith(stored, "/") then
+    return stored
+  end
+  if stored == "." then
+    return root
+  end
+  return root .. "/" .. stored
+end
+
+-- Storage scopes in precedence order
+local SCOPES = { "personal", "shared" }
+
+---Read the stored values of a group from every scope, personal first
+---@param target {root: string, path: string|nil, group: string|nil} Resolved target
+---@return {value: string, note: string|nil, priority: integer|nil, scope: "personal"|"shared"}[] values
+local function read_group(target)
+  local values = {}
+  for _, scope in ipairs(SCOPES) do
+    local store = get_scope_storage(target.root, scope)
+    local key = get_group_key(target, scope)
+    for _, item in ipairs(store and key and store:get(key) or {}) do
+      table.insert(values, {
+        value = entries.spec(item),
+        note = entries.note(item),
+        priority = entries.priority(item),
+        scope = scope,
+      })
+    end
+  end
+  return values
+end
+
+---Find a stored item in a group
+---@param context_group (string|table)[] Stored group
+---@param value string Entry to look for
+---@return integer|nil index
+local function find_item(context_group, value)
+  for i, item in ipairs(context_group) do
+    if entries.spec(item) == value then
+      return i
+    end
+  end
+  return nil
+end
+
+---Write groups to a store, describing every saved change for the undo history
+---Saving merges with the file on disk, so callers scan first and write once the scan is done
+---@param store Storage Storage to write to
+---@param scope "personal"|"shared" Storage scope
+---@param updates table<string, table|false> New groups by key, false to delete a group
+---@param changes HistoryChange[] Saved changes are appended here; failed writes are left out
+---@return boolean success Whether every group was saved
+local function write_groups(store, scope, updates, changes)
+  local success = true
+  for key, group in pairs(updates) do
+    local before = vim.deepcopy(store:get(key))
+    local saved
+    if group then
+      saved = store:set(key, group)
+    else
+      saved = store:delete(key)
+    end
+
+    if saved then
+      -- Undo writes back to this branch's groups even after another branch is checked out
+      local change = { scope = scope, branch = store.meta.branch, key = key, before = before, after = group or nil }
+      table.insert(changes, change)
+    end
+    success = saved and success
+  end
+  return success
+end
+
+---Save a group and record the edit in the project's undo history
+---Callers pass a changed copy; the stored group still holds the state before the edit
+---@param root string Canonical project root
+---@param scope "personal"|"shared" Storage scope
+---@param store Storage Storage of the scope
+---@param key string Storage key of the group
+---@param value table|nil New group, nil to delete it
+---@param label string Description of the edit for the history
+---@return boolean success
+local function save_group(root, scope, store, key, value, label)
+  local changes = {}
+  local success = write_groups(store, scope, { [key] = value or false }, changes)
+  if success then
+    history.record(root, label, changes)
+    notify_context_change()
+  end
+  return success
+end
+
+---Check whether user input is written as a rule rather than a single file
+---Rules are globs (`src/api/**/*.go`), directories (`src/api/`) and exclusions (`!**/*_test.go`)
+---@param input string Rule or file path as typed
+---@return boolean is_rule
+function M.is_rule_pattern(input)
+  return vim.startswith(input, "!") or vim.endswith(input, "/") or input:find("[%*%?%[]") ~= nil
+end
+
+---Get the rule a stored value stands for
+---Rules are marked when they are added, so files such as `pages/[id].tsx` are never taken for globs
+---@param value string Stored value
+---@return string|nil rule Rule as written, e.g. `!**/*_test.go`; nil for files and included groups
+function M.get_rule(value)
+  if vim.startswith(value, storage.RULE_PREFIX) then
+    return value:sub(#storage.RULE_PREFIX + 1)
+  end
+  return nil
+end
+
+---Check whether a stored value is a live rule rather than a single file
+---@param value string Stored value
+---@return boolean is_rule
+function M.is_context_rule(value)
+  return M.get_rule(value) ~= nil
+end
+
+---Get the absolute glob a rule stands for
+---@param rule string Stored glob or directory rule, without the exclusion prefix
+---@param root string Canonical project root
+---@return string pattern Absolute glob
+local function rule_pattern(rule, root)
+  -- The root is matched literally, even when it contains glob characters
+  local pattern = vim.startswith(rule, "/") and rule or utils.glob_escape(root) .. "/" .. rule
+  if vim.endswith(pattern, "/") then
+    pattern = pattern .. "**"
+  end
+  return pattern
+end
+
+---Evaluate a glob or directory rule against the files currently on disk
+---@param rule string Stored glob or directory rule
+---@param root string Canonical project root
+---@return string[] files Matching files, honoring ignore patterns
+local function evaluate_rule(rule, root)
+  local pattern = rule_pattern(rule, root)
+  -- Only scan below the literal directory prefix of the rule
+  local prefix = rule:match("^([^%*%?%[]*)/")
+  local base = prefix and prefix ~= "" and from_stored_path(prefix, root) or root
+
+  return vim.tbl_filter(function(file)
+    return utils.glob_match(pattern, file)
+  end, M.get_directory_files(base))
+end
+
+---@class ContextEntry
+---@field path string Absolute file path
+---@field scope "personal"|"shared" Scope the entry is stored in
+---@field group string|nil Named group the entry is inherited from
+---@field rule string|nil Rule the entry was matched by
+---@field value string|nil Stored entry, nil for rule matches
+---@field range integer[]|nil 1-based inclusive line range
+---@field symbol string|nil Symbol the entry is limited to
+---@field note string|nil Why the entry matters, inherited from the rule it was matched by
+---@field priority integer|nil Entries with lower priority are dropped first when exports exceed the size budget
+---@field paired boolean|nil Test or implementation of the buffer's file, included by the pairing rules
+
+---Expand a group into file entries, following references to named groups and evaluating rules
+---Exclusions of a group apply to everything the group contributes, including included groups
+---@param target {root: string, path: string|nil, group: string|nil} Group to expand
+---@param origin string|nil Named group the entries are inherited from
+---@param state {visiting: table<string, boolean>, expanded: table<string, boolean>}
+---@return ContextEntry[] entries Entries, possibly repeated
+local function expand_group(target, origin, state)
+  local result = {}
+  local exclusions = {}
+
+  for _, item in ipairs(read_group(target)) do
+    local value = item.value
+    local rule = M.get_rule(value)
+    if groups.is_named_key(value) then
+      local name = groups.name_from_key(value)
+      if state.visiting[name] then
+        vim.notify(
+          string.format("Context group cycle: %s includes %s again, skipped", origin or "group", name),
+          vim.log.levels.WARN
+        )
+      elseif not state.expanded[name] then
+        state.visiting[name] = true
+        state.expanded[name] = true
+        vim.list_extend(result, expand_group({ root = target.root, group = name }, name, state))
+        state.visiting[name] = nil
+      end
+    elseif rule and vim.startswith(rule, "!") then
+      table.insert(exclusions, rule_pattern(rule:sub(2), target.root))
+    elseif rule then
+      for _, path in ipairs(evaluate_rule(rule, target.root)) do
+        table.insert(result, {
+          path = path,
+          scope = item.scope,
+          group = origin,
+          rule = value,
+          note = item.note,
+          priority = item.priority,
+        })
+      end
+    else
+      local entry = entries.parse(value, target.root)
+      table.insert(result, {
+        path = from_stored_path(entry.file, target.root),
+        scope = item.scope,
+        group = origin,
+        value = value,
+        range = entry.range,
+        symbol = entry.symbol,
+        note = item.note,
+        priority = item.priority,
+      })
+    end
+  end
+
+  -- Test/implementation pairs of the buffer's own file are part of its group
+  if not origin and target.path and config.get().pairing.include then
+    for _, path in ipairs(pairing.find(utils.canonical_path(target.path), target.root)) do
+      table.insert(result, { path = path, scope = "personal", paired = true })
+    end
+  end
+
+  if #exclusions == 0 then
+    return result
+  end
+
+  return vim.tbl_filter(function(entry)
+    for _, pattern in ipairs(exclusions) do
+      if utils.glob_match(pattern, entry.path) then
+        return false
+      end
+    end
+    return true
+  end, result)
+end
+
+---Get context entries for a buffer with the scope each entry comes from
+---Personal entries are listed first; shared entries follow unless the same file is already in the personal group.
+---Included named groups are resolved transitively; their entries carry the name of the group they come from.
+---Glob and directory rules are evaluated on every call; their entries carry the rule they come from.
+---With `pairing.include` the test or implementation of the buffer's file is included as a paired entry.
+---Ranges of entries open in a buffer follow the buffer's edits.
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@param opts? {group: string} Options {group: Named group to use instead of the buffer's group}
+---@return ContextEntry[] entries Context entries
+function M.get_context_entries(bufnr, opts)
+  -- Callers such as exports and pickers run without a group too; commands report the missing group themselves
+  local target = resolve_target(bufnr, opts and opts.group)
+  if not target then
+    return {}
+  end
+
+  local state = { visiting = {}, expanded = {} }
+  if target.group then
+    state.visiting[target.group] = true
+    state.expanded[target.group] = true
+  end
+
+  local expanded = expand_group(target, nil, state)
+
+  -- The project-wide group is part of every other group
+  local always = config.get().always_include_group
+  if always and not state.expanded[always] and groups.exists(target.root, always) then
+    state.expanded[always] = true
+    state.visiting[always] = true
+    vim.list_extend(expanded, expand_group({ root = target.root, group = always }, always, state))
+  end
+
+  local result = {}
+  local seen = {}
+  for _, entry in ipairs(expanded) do
+    if entry.range then
+      entry.range = entries.get_tracked_range(entry.path, entry.value) or entry.range
+    end
+
+    -- Filter out duplicates and non-existent files
+    local id = entries.format({ file = entry.path, range = entry.range, symbol = entry.symbol })
+    if not seen[id] and vim.fn.filereadable(entry.path) == 1 then
+      seen[id] = true
+      table.insert(result, entry)
+    end
+  end
+
+  return result
+end
+
+---Get the test or implementation files paired with a buffer's file
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return string[] files Absolute paths of existing paired files
+function M.get_paired_files(bufnr)
+  local file_path = get_current_filepath(bufnr)
+  if not file_path or vim.fn.filereadable(file_path) ~= 1 then
+    return {}
+  end
+
+  file_path = utils.canonical_path(file_path)
+  return pairing.find(file_path, get_project_root(file_path))
+end
+
+---Get the project files a buffer's file depends on through its imports
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@param opts? {depth: integer} Options {depth: Maximum import hops (default config.import_graph.depth)}
+---@return GraphNode[] nodes Dependencies nearest first
+---@return boolean truncated Whether config.import_graph.max_files cut the closure short
+function M.get_dependencies(bufnr, opts)
+  local file_path = get_current_filepath(bufnr)
+  if not file_path or vim.fn.filereadable(file_path) ~= 1 then
+    return {}, false
+  end
+  return graph.closure(file_path, { depth = opts and opts.depth })
+end
+
+---Add the dependency closure of a buffer's file, or part of it, to its context group
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@param opts? {depth: integer, files: string[], scope: "personal"|"shared", group: string} Options {depth: Maximum import hops, files: Dependencies to add instead of the whole closure, scope: Storage scope, group: Named group to add to}
+---@return boolean success
+function M.add_dependencies(bufnr, opts)
+  opts = opts or {}
+  local files = vim.tbl_map(function(node)
+    return node.path
+  end, M.get_dependencies(bufnr, opts))
+  -- Selected dependencies keep the closure's nearest-first order, whatever order they were selected in
+  if opts.files then
+    local selected = {}
+    for _, file in ipairs(opts.files) do
+      selected[file] = true
+    end
+    files = vim.tbl_filter(function(file)
+      return selected[file]
+    end, files)
+    for _, file in ipairs(opts.files) do
+      if not vim.tbl_contains(files, file) then
+        table.insert(files, file)
+      end
+    end
+  end
+  if #files == 0 then
+    vim.notify("No project dependencies found", vim.log.levels.WARN)
+    return false
+  end
+
+  local result =
+    M.add_multiple_context_files(files, bufnr, { scope = opts.scope, group = opts.group, keep_order = true })
+  if not result.success then
+    vim.notify(table.concat(result.errors, "\n"), vim.log.levels.ERROR)
+    return false
+  end
+  vim.notify(string.format("Added %d dependencies to context group (%d already in it)", result.added, result.skipped))
+  return true
+end
+
+---Get the rules of a buffer's group: included groups, globs, directories and exclusions
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@param opts? {group: string} Options {group: Named group to use instead of the buffer's group}
+---@return {value: string, label: string, kind: "group"|"pattern"|"exclude", scope: "personal"|"shared", note: string|nil, priority: integer|nil}[] rules
+function M.get_group_rules(bufnr, opts)
+  local target = resolve_target(bufnr, opts and opts.group)
+  if not target then
+    return {}
+  end
+
+  local rules = {}
+  for _, item in ipairs(read_group(target)) do
+    local kind
+    local rule = M.get_rule(item.value)
+    if groups.is_named_key(item.value) then
+      kind = "group"
+    elseif rule and vim.startswith(rule, "!") then
+      kind = "exclude"
+    elseif rule then
+      kind = "pattern"
+    end
+    if kind then
+      table.insert(rules, {
+        value = item.value,
+        label = rule or groups.label(groups.name_from_key(item.value)),
+        kind = kind,
+        scope = item.scope,
+        note = item.note,
+        priority = item.priority,
+      })
+    end
+  end
+  return rules
+end
+
+---Check whether a named group includes another one, directly or transitively
+---@param root string Canonical project root
+---@param from string Named group to start from
+---@param to string Named group to look for
+---@return boolean reaches
+local function group_reaches(root, from, to)
+  local visited = {}
+  local function visit(name)
+    if name == to then
+      return true
+    end
+    if visited[name] then
+      return false
+    end
+    visited[name] = true
+    for _, item in ipairs(read_group({ root = root, group = name })) do
+      if groups.is_named_key(item.value) and visit(groups.name_from_key(item.value)) then
+        return true
+      end
+    end
+    return false
+  end
+  return visit(from)
+end
+
+---Append a stored value to a target's group unless already present
+---@param target {root: string, path: string|nil, group: string|nil} Resolved target
+---@param scope "personal"|"shared" Storage scope
+---@param value string Stored value
+---@return boolean success
+local function append_to_group(target, scope, value)
+  local store = get_scope_storage(target.root, scope)
+  local key = store and get_group_key(target, scope)
+  if not key then
+    vim.notify("Shared context groups are unavailable for this file", vim.log.levels.ERROR)
+    return false
+  end
+
+  local context_group = vim.deepcopy(store:get(key) or {})
+  if find_item(context_group, value) then
+    vim.notify("Already in context group: " .. value, vim.log.levels.INFO)
+    return false
+  end
+
+  table.insert(context_group, value)
+  return save_group(target.root, scope, store, key, context_group, "Add " .. value)
+end
+
+---Include a named group in a buffer's group
+---@param name string Named group to include
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default config.default_scope), group: Named group to add to}
+---@return boolean success
+function M.add_group_reference(name, target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    vim.notify("Cannot include group: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  if not groups.exists(target.root, name) then
+    vim.notify("Context group not found: " .. name, vim.log.levels.ERROR)
+    return false
+  end
+
+  if target.group and group_reaches(target.root, name, target.group) then
+    vim.notify(
+      string.format("Cannot include %s in %s: groups would include each other", name, target.group),
+      vim.log.levels.ERROR
+    )
+    return false
+  end
+
+  return append_to_group(target, resolve_scope(opts and opts.scope), groups.key(name))
+end
+
+---Add a glob, directory or exclusion rule to a buffer's group
+---Relative rules are relative to the project root
+---@param rule string Rule such as `src/api/**/*.go`, `src/api/` or `!**/*_test.go`
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default config.default_scope), group: Named group to add to}
+---@return boolean success
+function M.add_context_rule(rule, target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    vim.notify("Cannot add rule: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  if not M.is_rule_pattern(rule) then
+    vim.notify("Not a glob, directory or exclusion rule: " .. rule, vim.log.levels.ERROR)
+    return false
+  end
+
+  local scope = resolve_scope(opts and opts.scope)
+  local prefix = vim.startswith(rule, "!") and "!" or ""
+  local pattern = rule:sub(#prefix + 1)
+
+  -- Store rules inside the project relative to its root
+  if vim.startswith(pattern, target.root .. "/") then
+    pattern = pattern:sub(#target.root + 2)
+  elseif vim.startswith(pattern, "/") and scope == "shared" then
+    vim.notify("Cannot add rule: Shared context groups only hold project rules", vim.log.levels.ERROR)
+    return false
+  end
+
+  return append_to_group(target, scope, storage.RULE_PREFIX .. prefix .. pattern)
+end
+
+---Exclude a single file, such as one matched by a rule or paired with the buffer, from a buffer's group
+---@param path string File path
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default config.default_scope), group: Named group to add to}
+---@return boolean success
+function M.exclude_file(path, target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  local stored = target and to_stored_path(path, target.root, resolve_scope(opts and opts.scope))
+  if not stored then
+    vim.notify("Cannot exclude " .. path .. " from this context group", vim.log.levels.ERROR)
+    return false
+  end
+  return M.add_context_rule("!" .. utils.glob_escape(stored), target_bufnr, opts)
+end
+
+---Remove a rule (included group, glob, directory or exclusion) from a buffer's group
+---@param value string Stored rule as returned by get_group_rules, or a rule as written
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the rule), group: Named group to remove from}
+---@return boolean success
+function M.remove_context_rule(value, target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    return false
+  end
+
+  for _, scope in ipairs(opts and opts.scope and { opts.scope } or SCOPES) do
+    local store = get_scope_storage(target.root, scope)
+    local key = store and get_group_key(target, scope)
+    local context_group = key and vim.deepcopy(store:get(key))
+    local index = context_group and find_item(context_group, value)
+    if not index and context_group and M.is_rule_pattern(value) then
+      index = find_item(context_group, storage.RULE_PREFIX .. value)
+    end
+    if index then
+      table.remove(context_group, index)
+      return save_group(target.root, scope, store, key, context_group, "Remove " .. value)
+    end
+  end
+
+  return false
+end
+
+---Find the stored item of an entry or rule and save the group after changing it
+---@param value string Stored entry or rule
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the entry), group: Named group to edit}
+---@param label string Description of the edit for the history
+---@param update fun(context_group: (string|table)[], index: integer): boolean Changes the group, returns false to skip saving
+---@return boolean success
+local function update_item(value, target_bufnr, opts, label, update)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    vim.notify("Cannot edit context group: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  for _, scope in ipairs(opts and opts.scope and { opts.scope } or SCOPES) do
+    local store = get_scope_storage(target.root, scope)
+    local key = store and get_group_key(target, scope)
+    local context_group = key and vim.deepcopy(store:get(key))
+    local index = context_group and find_item(context_group, value)
+    if index then
+      if not update(context_group, index) then
+        return false
+      end
+      return save_group(target.root, scope, store, key, context_group, label)
+    end
+  end
+
+  vim.notify("Not in context group: " .. value, vim.log.levels.ERROR)
+  return false
+end
+
+---Change the metadata of a stored item
+---@param item string|table Stored item
+---@param changes table Metadata fields to set, `false` removes a field
+---@return string|table item
+local function with_meta(item, changes)
+  local meta = type(item) == "table" and vim.deepcopy(item) or {}
+  meta.entry = nil
+  for field, change in pairs(changes) do
+    meta[field] = change or nil
+  end
+  return entries.make(entries.spec(item), meta)
+end
+
+---Set or clear the note of an entry or rule
+---@param value string Stored entry or rule
+---@param note string|nil Note, nil or empty to clear it
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the entry), group: Named group to edit}
+---@return boolean success
+function M.set_entry_note(value, note, target_bufnr, opts)
+  note = note and vim.trim(note) or ""
+  return update_item(value, target_bufnr, opts, "Note " .. value, function(context_group, index)
+    context_group[index] = with_meta(context_group[index], { note = note ~= "" and note })
+    return true
+  end)
+end
+
+---Set or clear the priority of an entry or rule
+---Entries with lower priority are dropped first when an export exceeds config.size_budget
+---@param value string Stored entry or rule
+---@param priority integer|nil Priority, nil or 0 for the default
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the entry), group: Named group to edit}
+---@return boolean success
+function M.set_entry_priority(value, priority, target_bufnr, opts)
+  return update_item(value, target_bufnr, opts, "Priority " .. value, function(context_group, index)
+    context_group[index] = with_meta(context_group[index], { priority = priority ~= 0 and priority })
+    return true
+  end)
+end
+
+---Check whether a stored value is a rule rather than a file entry
+---@param value string Stored value
+---@return boolean
+local function is_rule_value(value)
+  return groups.is_named_key(value) or M.is_context_rule(value)
+end
+
+---Move an entry or rule within its group
+---Files move past files and rules past rules, matching the order the picker shows
+---@param value string Stored entry or rule
+---@param direction "up"|"down"|"top" Where to move it
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the entry), group: Named group to edit}
+---@return boolean success
+function M.move_entry(value, direction, target_bufnr, opts)
+  return update_item(value, target_bufnr, opts, "Move " .. value .. " " .. direction, function(context_group, index)
+    local is_rule = is_rule_value(value)
+    local step = direction == "down" and 1 or -1
+    local destination
+
+    local i = index + step
+    while context_group[i] ~= nil do
+      if is_rule_value(entries.spec(context_group[i])) == is_rule then
+        destination = i
+        if direction ~= "top" then
+          break
+        end
+      end
+      i = i + step
+    end
+
+    if not destination then
+      return false
+    end
+
+    table.insert(context_group, destination, table.remove(context_group, index))
+    return true
+  end)
+end
+
+---Stop including a named group in a buffer's group
+---@param name string Included named group
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the reference), group: Named group to remove from}
+---@return boolean success
+function M.remove_group_reference(name, target_bufnr, opts)
+  return M.remove_context_rule(groups.key(name), target_bufnr, opts)
+end
+
 ---Get context files for a buffer
 ---@param bufnr integer|nil Buffer number (nil for current buffer)
----@param opts? {relative: boolean} Options {relative: Return relative paths (default false)}
+---@param opts? {relative: boolean, group: string} Options {relative: Return relative paths (default false), group: Named group to use}
 ---@return string[] context_files List of context files
 function M.get_context_files(bufnr, opts)
+  local files = {}
+  local seen = {}
+
+  -- Files with several range entries are listed once
+  for _, entry in ipairs(M.get_context_entries(bufnr, opts)) do
+    if not seen[entry.path] then
+      seen[entry.path] = true
+      table.insert(files, opts and opts.relative and M.get_relative_path(entry.path) or entry.path)
+    end
+  end
+
+  return files
+end
+
+---Get the line range of a context entry, resolving symbols
+---@param entry ContextEntry Context entry
+---@return integer[]|nil range 1-based inclusive line range, nil for whole files
+function M.get_entry_range(entry)
+  if entry.symbol then
+    local range = entries.resolve_symbol(entry.path, entry.symbol)
+    if not range then
+      vim.notify(string.format("Symbol %s not found in %s", entry.symbol, entry.path), vim.log.levels.WARN)
+    end
+    return range
+  end
+  return entry.range
+end
+
+---Get context contents for a buffer
+---Range and symbol entries contain only their lines, prefixed with line numbers
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return table[] File contents with metadata
+function M.get_context_contents(bufnr)
+  local contents = {}
+
+  for _, entry in ipairs(M.get_context_entries(bufnr)) do
+    local file_path = entry.path
+    local range = M.get_entry_range(entry)
+    local content
+    if range then
+      local lines = entries.read_lines(file_path)
+      content = lines and entries.render_range(lines, range)
+    else
+      content = utils.read_file_content(file_path)
+    end
+
+    if content then
+      local name = M.get_relative_path(file_path)
+      table.insert(contents, {
+        path = file_path,
+        name = range and entries.format({ file = name, range = range }) or name,
+        content = content,
+        filetype = vim.filetype.match({ filename = file_path }) or "",
+        modified = vim.fn.getftime(file_path),
+        range = range,
+        symbol = entry.symbol,
+        note = entry.note,
+        priority = entry.priority,
+      })
+    end
+  end
+
+  return contents
+end
+
+---Drop the lowest-priority items until their content fits the size budget
+---Among equal priorities the items listed last are dropped first; kept items stay in order
+---@generic T: {content: string, priority: number|nil}
+---@param items T[] Items in export order
+---@param budget? integer|false Budget in bytes (default config.size_budget)
+---@return T[] kept, T[] dropped
+function M.apply_size_budget(items, budget)
+  if budget == nil then
+    budget = config.get().size_budget
+  end
+
+  local total = 0
+  for _, item in ipairs(items) do
+    total = total + #item.content
+  end
+  if not budget or total <= budget then
+    return items, {}
+  end
+
+  local candidates = {}
+  for index, item in ipairs(items) do
+    table.insert(candidates, { index = index, priority = item.priority or 0 })
+  end
+  table.sort(candidates, function(a, b)
+    if a.priority ~= b.priority then
+      return a.priority < b.priority
+    end
+    return a.index > b.index
+  end)
+
+  local drop = {}
+  for _, candidate in ipairs(candidates) do
+    if total <= budget then
+      break
+    end
+    drop[candidate.index] = true
+    total = total - #items[candidate.index].content
+  end
+
+  local kept, dropped = {}, {}
+  for index, item in ipairs(items) do
+    table.insert(drop[index] and dropped or kept, item)
+  end
+
+  vim.notify(
+    string.format("Size budget of %d bytes exceeded, dropped %d low-priority files", budget, #dropped),
+    vim.log.levels.WARN
+  )
+  return kept, dropped
+end
+
+---Get project files
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return string[] project_files List of project files
+function M.get_project_files(bufnr)
   local file_path = get_current_filepath(bufnr)
   if not file_path then
     vim.notify("No valid file path found", vim.log.levels.WARN)
     return {}
   end
 
-  local root = M.find_root(file_path)
-  local store = storage.get_storage(root)
+  local root = M.find_root(file_path)
+  local ignore_patterns = config.get().import_prefs.ignore_patterns or {}
+
+  -- Get all project files
+  local all_files = M.get_files(root)
+
+  -- Filter out ignored files
+  return vim.tbl_filter(function(file)
+    -- Check ignore patterns
+    for _, pattern in ipairs(ignore_patterns) do
+      if file:match(pattern) then
+        return false
+      end
+    end
+    -- Ensure file is readable
+    return vim.fn.filereadable(file) == 1
+  end, all_files)
+end
+
+
+---Add file to context group
+---A line range (`file.go:120-180`) or symbol (`file.go#Server.Start`) limits the entry to part of the file
+---@param file string File to add
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default config.default_scope), group: Named group to add to}
+---@return boolean success
+function M.add_context_file(file, target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    vim.notify("Cannot add to context: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  local root = target.root
+  local scope = resolve_scope(opts and opts.scope)
+  local store = get_scope_storage(root, scope)
+  if not store then
+    vim.notify("Cannot add to context: Shared context groups are disabled", vim.log.levels.ERROR)
+    return false
+  end
+
+  local entry = entries.parse(file, vim.fn.getcwd())
+
+  -- Ensure file exists and is readable
+  if not utils.read_file_content(entry.file) then
+    vim.notify("Cannot add to context: File not readable: " .. entry.file, vim.log.levels.ERROR)
+    return false
+  end
+
+  if entry.range and entry.range[1] > entry.range[2] then
+    vim.notify("Cannot add to context: Invalid line range: " .. file, vim.log.levels.ERROR)
+    return false
+  end
+
+  if entry.symbol and not entries.resolve_symbol(vim.fn.fnamemodify(entry.file, ":p"), entry.symbol) then
+    vim.notify("Cannot add to context: Symbol not found: " .. entry.symbol, vim.log.levels.ERROR)
+    return false
+  end
+
+  local key = get_group_key(target, scope)
+  local stored_file = to_stored_path(entry.file, root, scope)
+  if not key or not stored_file then
+    vim.notify("Cannot add to context: Shared context groups only hold project files", vim.log.levels.ERROR)
+    return false
+  end
+  stored_file = entries.format({ file = stored_file, range = entry.range, symbol = entry.symbol })
+
+  -- Get current context group
+  local context_group = vim.deepcopy(store:get(key) or {})
+
+  -- Check if file already in context group
+  if find_item(context_group, stored_file) then
+    vim.notify("File already in context group: " .. file, vim.log.levels.INFO)
+    return false
+  end
+
+  -- Add file to context group
+  table.insert(context_group, stored_file)
+
+  -- Save updated context group
+  local success = save_group(root, scope, store, key, context_group, "Add " .. stored_file)
+  if success and entry.range then
+    M.track_buffer_entries(vim.fn.bufnr(entry.file))
+  end
+
+  return success
+end
+
+---Remove file from context group
+---@param file string File to remove
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options {scope: Storage scope (default: whichever scope holds the file), group: Named group to remove from}
+---@return boolean success
+function M.remove_context_file(file, target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    vim.notify("Cannot remove from context: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  local root = target.root
+  local scopes = opts and opts.scope and { opts.scope } or SCOPES
+  local entry = entries.parse(file, vim.fn.getcwd())
+
+  for _, scope in ipairs(scopes) do
+    local store = get_scope_storage(root, scope)
+    local key = get_group_key(target, scope)
+    local stored_file = to_stored_path(entry.file, root, scope)
+    if stored_file then
+      stored_file = entries.format({ file = stored_file, range = entry.range, symbol = entry.symbol })
+    end
+    local context_group = store and key and vim.deepcopy(store:get(key))
+
+    -- Find and remove file
+    local index = context_group and stored_file and find_item(context_group, stored_file)
+    if index then
+      table.remove(context_group, index)
+
+      -- Save updated context group
+      return save_group(root, scope, store, key, context_group, "Remove " .. stored_file)
+    end
+  end
+
+  return false
+end
+
+---Clear context group
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared"|"all", group: string} Options {scope: Storage scope (default config.default_scope), group: Named group to clear}
+---@return boolean|nil success Nil when the group was already empty
+function M.clear_context_group(target_bufnr, opts)
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    vim.notify("Cannot clear context: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  local requested = opts and opts.scope
+  local scopes = requested == "all" and SCOPES or { resolve_scope(requested) }
+
+  -- Remove context group; named groups are emptied but kept
+  local success = true
+  local changes = {}
+  local cleared = false
+  for _, scope in ipairs(scopes) do
+    local store = get_scope_storage(target.root, scope)
+    local key = get_group_key(target, scope)
+    local group = store and key and store:get(key)
+    if group and not (target.group and #group == 0) then
+      cleared = true
+      success = write_groups(store, scope, { [key] = target.group and {} or false }, changes) and success
+    end
+  end
+  if not cleared then
+    vim.notify("Context group is already empty", vim.log.levels.INFO)
+    return nil
+  end
+
+  -- Clearing every scope is undone as one edit, holding only the scopes that were cleared
+  history.record(target.root, "Clear " .. (target.group and groups.label(target.group) or "group"), changes)
+
+  if success then
+    notify_context_change()
+  end
+
+  return success
+end
 
-  -- Get context group for current file
-  local context_group = store:get(file_path) or {}
-
-  -- Filter out non-existent files and convert to relative paths if needed
-  return vim.tbl_map(
-    function(file)
-      -- Only process existing files
-      if vim.fn.filereadable(file) == 1 and opts and opts.relative then
-        return M.get_relative_path(file)
-      end
-      return file
-    end,
-    vim.tbl_filter(function(file)
-      return vim.fn.filereadable(file) == 1
-    end, context_group)
-  )
+---Undo the last context group edit of a buffer's project
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return boolean success
+function M.undo(bufnr)
+  local root = M.get_current_root(bufnr)
+  local entry, success
+  if root then
+    entry, success = history.undo(root)
+  end
+  if not entry then
+    vim.notify("Nothing to undo", vim.log.levels.INFO)
+    return false
+  elseif not success then
+    vim.notify("Could not undo: " .. entry.label, vim.log.levels.ERROR)
+    return false
+  end
+
+  vim.notify("Undid: " .. entry.label)
+  return true
 end
 
----Get context contents for a buffer
+---Redo the last undone context group edit of a buffer's project
 ---@param bufnr integer|nil Buffer number (nil for current buffer)
----@return table[] File contents with metadata
-function M.get_context_contents(bufnr)
-  local files = M.get_context_files(bufnr)
-  local contents = {}
+---@return boolean success
+function M.redo(bufnr)
+  local root = M.get_current_root(bufnr)
+  local entry, success
+  if root then
+    entry, success = history.redo(root)
+  end
+  if not entry then
+    vim.notify("Nothing to redo", vim.log.levels.INFO)
+    return false
+  elseif not success then
+    vim.notify("Could not redo: " .. entry.label, vim.log.levels.ERROR)
+    return false
+  end
 
-  for _, file_path in ipairs(files) do
-    local content = utils.read_file_content(file_path)
-    if content then
-      table.insert(contents, {
-        path = file_path,
-        name = M.get_relative_path(file_path),
-        content = content,
-        filetype = vim.filetype.match({ filename = file_path }) or "",
-        modified = vim.fn.getftime(file_path),
-      })
+  vim.notify("Redid: " .. entry.label)
+  return true
+end
+
+---Copy personal groups from another branch into the checked out branch's groups
+---Entries the current group already has are kept, so copying twice changes nothing
+---@param branch string Branch to copy from
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {group: string, all: boolean} Options {group: Named group to copy, all: Copy every group of the branch}
+---@return boolean success
+function M.copy_from_branch(branch, target_bufnr, opts)
+  opts = opts or {}
+  if not config.get().branch_groups then
+    vim.notify("Branch groups are disabled, see branch_groups", vim.log.levels.ERROR)
+    return false
+  end
+
+  local target = resolve_target(target_bufnr, opts.group)
+  local root = target and target.root or M.get_current_root(target_bufnr)
+  if not root then
+    vim.notify("No valid file path found", vim.log.levels.ERROR)
+    return false
+  end
+
+  if not target and not opts.all then
+    vim.notify("Cannot copy context: No file open and no named group active", vim.log.levels.ERROR)
+    return false
+  end
+
+  local source = storage.get_branch_storage(root, branch)
+  local store = storage.get_storage(root)
+  if source == store then
+    vim.notify("Context groups already follow branch " .. branch, vim.log.levels.WARN)
+    return false
+  end
+
+  local keys = opts.all and vim.tbl_keys(source:all()) or { get_group_key(target, "personal") }
+  local changes = {}
+  local copied = 0
+  for _, key in ipairs(keys) do
+    local context_group = vim.deepcopy(store:get(key) or {})
+    local added = 0
+    for _, item in ipairs(source:get(key) or {}) do
+      if not find_item(context_group, entries.spec(item)) then
+        table.insert(context_group, vim.deepcopy(item))
+        added = added + 1
+      end
+    end
+
+    if added > 0 and write_groups(store, "personal", { [key] = context_group }, changes) then
+      copied = copied + added
     end
   end
 
-  return contents
+  if copied == 0 then
+    vim.notify("Nothing to copy from branch " .. branch, vim.log.levels.INFO)
+    return false
+  end
+
+  history.record(root, "Copy groups from branch " .. branch, changes)
+  notify_context_change()
+  vim.notify(string.format("Copied %d entries from branch %s", copied, branch))
+  return true
 end
 
----Get project files
----@param bufnr integer|nil Buffer number (nil for current buffer)
----@return string[] project_files List of project files
-function M.get_project_files(bufnr)
-  local file_path = get_current_filepath(bufnr)
-  if not file_path then
-    vim.notify("No valid file path found", vim.log.levels.WARN)
-    return {}
+---Save groups to a portable bundle file with root-relative paths, notes, priorities and ranges
+---Named groups included by a saved group are saved with it. Entries outside the project are left out.
+---@param file string Bundle file, YAML for `.yaml`/`.yml` and JSON otherwise
+---@param target_bufnr integer|nil Target buffer number
+---@param opts? {group: string, all: boolean} Options {group: Named group to save, all: Save every group of the project}
+---@return boolean success
+function M.save_bundle(file, target_bufnr, opts)
+  opts = opts or {}
+  local target = resolve_target(target_bufnr, opts.group)
+  local root = target and target.root or (opts.all and M.get_current_root(target_bufnr))
+  if not root then
+    vim.notify("No file open and no named context group active", vim.log.levels.ERROR)
+    return false
   end
 
-  local root = M.find_root(file_path)
-  local ignore_patterns = config.get().import_prefs.ignore_patterns or {}
+  local bundle_groups = {}
+  local skipped = 0
+  local function add_group(key)
+    if bundle_groups[key] then
+      return
+    end
 
-  -- Get all project files
-  local all_files = M.get_files(root)
+    local items = {}
+    bundle_groups[key] = items
+    for _, scope in ipairs(SCOPES) do
+      local store = get_scope_storage(root, scope)
+      for _, item in ipairs(store and store:get(key) or {}) do
+        local value = entries.spec(item)
+        if vim.startswith(((M.get_rule(value) or value):gsub("^!", "")), "/") then
+          skipped = skipped + 1
+        elseif not find_item(items, value) then
+          table.insert(items, vim.deepcopy(item))
+        end
+      end
+    end
 
-  -- Filter out ignored files
-  return vim.tbl_filter(function(file)
-    -- Check ignore patterns
-    for _, pattern in ipairs(ignore_patterns) do
-      if file:match(pattern) then
-        return false
+    -- Included named groups travel with the group
+    for _, item in ipairs(items) do
+      if groups.is_named_key(entries.spec(item)) then
+        add_group(entries.spec(item))
       end
     end
-    -- Ensure file is readable
-    return vim.fn.filereadable(file) == 1
-  end, all_files)
+  end
+
+  if opts.all then
+    for _, scope in ipairs(SCOPES) do
+      local store = get_scope_storage(root, scope)
+      for key in pairs(store and store:all() or {}) do
+        if not vim.startswith(key, "/") then
+          add_group(key)
+        end
+      end
+    end
+  else
+    -- The shared form of a key is root-relative, or nil for files outside the project
+    local key = get_group_key(target, "shared")
+    if key then
+      add_group(key)
+    end
+  end
+
+  if next(bundle_groups) == nil then
+    vim.notify("No context group to save", vim.log.levels.WARN)
+    return false
+  end
+
+  file = vim.fn.fnamemodify(file, ":p")
+  if not bundles.write(file, bundle_groups) then
+    vim.notify("Failed to write " .. file, vim.log.levels.ERROR)
+    return false
+  end
+
+  local message = string.format("Saved %d context groups to %s", vim.tbl_count(bundle_groups), file)
+  if skipped > 0 then
+    message = message .. string.format(" (%d entries outside the project left out)", skipped)
+  end
+  vim.notify(message)
+  return true
 end
 
+-- Conflict policies for groups that exist both in a bundle and in storage
+local LOAD_POLICIES = { merge = true, replace = true, skip = true }
 
----Add file to context group
----@param file string File to add
+---Load groups from a bundle file into the current project
+---Existing groups are merged with the bundle's (keeping their own metadata), replaced by it, or skipped.
+---@param file string Bundle file
 ---@param target_bufnr integer|nil Target buffer number
+---@param opts? {policy: "merge"|"replace"|"skip", scope: "personal"|"shared"} Options {policy: Conflict policy (default merge)}
 ---@return boolean success
-function M.add_context_file(file, target_bufnr)
-  local target_path = get_current_filepath(target_bufnr) or file
-  if not target_path then
-    vim.notify("Cannot add to context: No valid file path found", vim.log.levels.ERROR)
+function M.load_bundle(file, target_bufnr, opts)
+  opts = opts or {}
+  local policy = opts.policy or "merge"
+  if not LOAD_POLICIES[policy] then
+    vim.notify("Unknown conflict policy: " .. policy .. " (merge, replace or skip)", vim.log.levels.ERROR)
     return false
   end
 
-  local root = M.find_root(target_path)
-  local store = storage.get_storage(root)
+  local root = M.get_current_root(target_bufnr)
+  if not root then
+    vim.notify("No valid file path found", vim.log.levels.ERROR)
+    return false
+  end
 
-  -- Ensure file exists and is readable
-  if not utils.read_file_content(file) then
-    vim.notify("Cannot add to context: File not readable: " .. file, vim.log.levels.ERROR)
+  local scope = resolve_scope(opts.scope)
+  local store = get_scope_storage(root, scope)
+  if not store then
+    vim.notify("Shared context groups are disabled", vim.log.levels.ERROR)
     return false
   end
 
-  -- Get current context group
-  local context_group = store:get(target_path) or {}
+  local bundle, err = bundles.read(vim.fn.fnamemodify(file, ":p"))
+  if not bundle then
+    vim.notify(string.format("Cannot load %s: %s", file, err), vim.log.levels.ERROR)
+    return false
+  end
 
-  -- Check if file already in context group
-  for _, existing_file in ipairs(context_group) do
-    if existing_file == file then
-      vim.notify("File already in context group: " .. file, vim.log.levels.INFO)
-      return false
+  local keys = vim.tbl_keys(bundle.groups)
+  table.sort(keys)
+
+  local changes = {}
+  local skipped = 0
+  for _, key in ipairs(keys) do
+    local existing = store:get(key)
+    local context_group
+    if existing == nil or policy == "replace" then
+      context_group = vim.deepcopy(bundle.groups[key])
+    elseif policy == "merge" then
+      context_group = vim.deepcopy(existing)
+      for _, item in ipairs(bundle.groups[key]) do
+        if not find_item(context_group, entries.spec(item)) then
+          table.insert(context_group, vim.deepcopy(item))
+        end
+      end
+    else
+      skipped = skipped + 1
+    end
+
+    if context_group and not vim.deep_equal(context_group, existing) then
+      write_groups(store, scope, { [key] = context_group }, changes)
     end
   end
 
-  -- Add file to context group
-  table.insert(context_group, file)
+  if #changes > 0 then
+    history.record(root, "Load " .. vim.fn.fnamemodify(file, ":t"), changes)
+    notify_context_change()
+  end
 
-  -- Save updated context group
-  local success = store:set(target_path, context_group)
+  local message = string.format("Loaded %d of %d context groups from %s", #changes, #keys, file)
+  if skipped > 0 then
+    message = message .. string.format(" (%d existing groups skipped)", skipped)
+  end
+  vim.notify(message)
+  return true
+end
 
-  -- Trigger configured callback function
-  local cfg = config.get()
-  if success and cfg.on_context_change then
-    cfg.on_context_change()
+---Track the range entries of a buffer's file with extmarks so they follow edits
+---@param bufnr integer Buffer number
+function M.track_buffer_entries(bufnr)
+  if bufnr == -1 or not vim.api.nvim_buf_is_loaded(bufnr) then
+    return
   end
 
-  return success
+  local path = vim.api.nvim_buf_get_name(bufnr)
+  if path == "" or vim.fn.filereadable(path) ~= 1 then
+    return
+  end
+
+  path = utils.canonical_path(path)
+  local root = get_project_root(path)
+  for _, scope in ipairs(SCOPES) do
+    local store = get_scope_storage(root, scope)
+    for _, context_group in pairs(store and store:all() or {}) do
+      for _, item in ipairs(context_group) do
+        local value = entries.spec(item)
+        local entry = entries.parse(value, root)
+        if entry.range and from_stored_path(entry.file, root) == path then
+          entries.track(bufnr, value, entry.range)
+        end
+      end
+    end
+  end
 end
 
+---Store the ranges of a buffer's tracked entries once its edits are written
+---@param bufnr integer Buffer number
+function M.sync_buffer_entries(bufnr)
+  local moved = entries.take_moved(bufnr)
+  if #moved == 0 then
+    return
+  end
 
----Remove file from context group
----@param file string File to remove
----@param target_bufnr integer|nil Target buffer number
----@return boolean success
-function M.remove_context_file(file, target_bufnr)
-  local target_path = get_current_filepath(target_bufnr)
-  if not target_path then
-    vim.notify("Cannot remove from context: No valid file path found", vim.log.levels.ERROR)
-    return false
+  local renames = {}
+  for _, item in ipairs(moved) do
+    renames[item.old] = item.new
   end
 
-  local root = M.find_root(target_path)
-  local store = storage.get_storage(root)
+  local root = get_project_root(vim.api.nvim_buf_get_name(bufnr))
+  for _, scope in ipairs(SCOPES) do
+    local store = get_scope_storage(root, scope)
+    local updates = {}
+    for key, context_group in pairs(store and store:all() or {}) do
+      for i, item in ipairs(context_group) do
+        local renamed = renames[entries.spec(item)]
+        if renamed then
+          -- Keep the item's metadata; the group may belong to the default branch's storage
+          updates[key] = updates[key] or vim.deepcopy(context_group)
+          updates[key][i] = type(item) == "table" and vim.tbl_extend("force", item, { entry = renamed }) or renamed
+        end
+      end
+    end
+    -- Saving merges with the file on disk, so keys are only written once the scan is done
+    for key, context_group in pairs(updates) do
+      store:set(key, context_group)
+    end
+  end
 
-  -- Get current context group
-  local context_group = store:get(target_path)
-  if not context_group then
+  notify_context_change()
+end
+
+---Rewrite a stored path after a file or directory was renamed
+---@param path string Stored path
+---@param renames {old: string, new: string}[] Stored paths before and after the rename
+---@return string|nil renamed Nil when the path is not affected
+local function rename_path(path, renames)
+  for _, rename in ipairs(renames) do
+    if path == rename.old then
+      return rename.new
+    end
+    if vim.startswith(path, rename.old .. "/") then
+      return rename.new .. path:sub(#rename.old + 1)
+    end
+  end
+  return nil
+end
+
+---Rewrite a stored value after a rename: entries keep their range or symbol, rules keep their glob
+---@param value string Stored value
+---@param renames {old: string, new: string}[] Stored paths before and after the rename
+---@return string|nil renamed Nil when the value is not affected
+local function rename_value(value, renames)
+  if groups.is_named_key(value) then
+    return nil
+  end
+
+  local rule = M.get_rule(value)
+  if rule then
+    local prefix = vim.startswith(rule, "!") and "!" or ""
+    local renamed = rename_path(rule:sub(#prefix + 1), renames)
+    return renamed and storage.RULE_PREFIX .. prefix .. renamed
+  end
+
+  -- A file whose name contains `#` is renamed as a whole
+  local whole = rename_path(value, renames)
+  if whole then
+    return whole
+  end
+
+  local entry = entries.parse(value)
+  local file = rename_path(entry.file, renames)
+  if not file then
+    return nil
+  end
+  entry.file = file
+  return entries.format(entry)
+end
+
+---Rewrite group keys and entries of a project after files or directories were renamed
+---Groups of a renamed file are merged into any group the new path already has. The rewrite is one undo step.
+---@param root string Canonical project root
+---@param renames {old: string, new: string}[] Absolute paths before and after the rename
+---@return integer count Number of rewritten keys and entries
+function M.rename_paths(root, renames)
+  local changes = {}
+  local count = 0
+
+  for _, scope in ipairs(SCOPES) do
+    local store = get_scope_storage(root, scope)
+    local stored = {}
+    for _, rename in ipairs(renames) do
+      local old = to_stored_path(rename.old, root, scope)
+      local new = to_stored_path(rename.new, root, scope)
+      if old and new and old ~= new then
+        table.insert(stored, { old = old, new = new })
+      end
+    end
+
+    -- Rewrite the entries of every group first, then move the groups of renamed keys
+    local renamed_groups = {}
+    local new_keys = {}
+    local updates = {}
+    for key, context_group in pairs(store and #stored > 0 and store:all() or {}) do
+      local renamed_group = {}
+      local changed = false
+      for _, item in ipairs(context_group) do
+        local renamed = rename_value(entries.spec(item), stored)
+        if renamed then
+          count = count + 1
+          changed = true
+          -- Keep the item's metadata
+          item = type(item) == "table" and vim.tbl_extend("force", item, { entry = renamed }) or renamed
+        end
+        table.insert(renamed_group, vim.deepcopy(item))
+      end
+
+      renamed_groups[key] = renamed_group
+      new_keys[key] = not groups.is_named_key(key) and rename_path(key, stored) or key
+      if changed and new_keys[key] == key then
+        updates[key] = renamed_group
+      end
+    end
+
+    local deleted = {}
+    for key, new_key in pairs(new_keys) do
+      if new_key ~= key then
+        count = count + 1
+        deleted[key] = true
+        local kept = new_keys[new_key] == new_key and renamed_groups[new_key]
+        local merged = updates[new_key] or vim.deepcopy(kept or {})
+        for _, item in ipairs(renamed_groups[key]) do
+          if not find_item(merged, entries.spec(item)) then
+            table.insert(merged, item)
+          end
+        end
+        updates[new_key] = merged
+      end
+    end
+
+    for key in pairs(deleted) do
+      updates[key] = updates[key] or false
+    end
+    if store then
+      write_groups(store, scope, updates, changes)
+    end
+  end
+
+  if #changes > 0 then
+    local label = #renames == 1
+        and string.format("Rename %s to %s", M.get_relative_path(renames[1].old), M.get_relative_path(renames[1].new))
+      or string.format("Rename %d paths", #renames)
+    history.record(root, label, changes)
+    notify_context_change()
+  end
+  return count
+end
+
+---Update context groups after a file or directory was renamed, e.g. from a file explorer
+---@param old string Absolute path before the rename
+---@param new string Absolute path after the rename
+---@return boolean success True when a group referred to the old path
+function M.rename_file(old, new)
+  local root = get_project_root(new)
+  local count = M.rename_paths(root, { { old = utils.canonical_path(old), new = utils.canonical_path(new) } })
+  if count > 0 then
+    vim.notify(string.format("Context groups: %d entries follow %s", count, M.get_relative_path(new)))
+  end
+  return count > 0
+end
+
+-- Missing files already looked up per project, so git is asked about each file once per session
+---@type table<string, table<string, boolean>>
+local rename_checked = {}
+
+---Find where missing stored files were renamed to and rewrite every group that refers to them
+---Tells the user which entries were repaired and which could not be found
+---@param root string Canonical project root
+---@param missing string[] Absolute paths of missing files
+---@param force? boolean Look up files that were already looked up this session
+---@return boolean repaired True when entries were rewritten
+function M.repair_renames(root, missing, force)
+  rename_checked[root] = rename_checked[root] or {}
+  local checked = rename_checked[root]
+
+  local unchecked = {}
+  for _, path in ipairs(missing) do
+    if force or not checked[path] then
+      checked[path] = true
+      table.insert(unchecked, path)
+    end
+  end
+  if #unchecked == 0 then
     return false
   end
 
-  -- Find and remove file
-  local removed = false
-  for i, existing_file in ipairs(context_group) do
-    if existing_file == file then
-      table.remove(context_group, i)
-      removed = true
-      break
+  local relative = {}
+  for _, path in ipairs(unchecked) do
+    if vim.startswith(path, root .. "/") then
+      table.insert(relative, path:sub(#root + 2))
+    end
+  end
+  local found = project.detect_renames(root, relative)
+
+  local renames = {}
+  local lines = {}
+  local dropped = 0
+  for _, path in ipairs(unchecked) do
+    local rel_path = M.get_relative_path(path)
+    local new = found[path:sub(#root + 2)]
+    if new then
+      table.insert(renames, { old = path, new = root .. "/" .. new })
+      table.insert(lines, string.format("  %s -> %s", rel_path, new))
+    else
+      dropped = dropped + 1
+      table.insert(lines, string.format("  %s (not found, left out)", rel_path))
     end
   end
 
-  if not removed then
+  if #renames > 0 then
+    M.rename_paths(root, renames)
+  end
+
+  local summary = string.format("Context groups: %d renamed files repaired, %d dropped", #renames, dropped)
+  vim.notify(summary .. "\n" .. table.concat(lines, "\n"), dropped > 0 and vim.log.levels.WARN or vim.log.levels.INFO)
+  return #renames > 0
+end
+
+---Look for renames of every missing file stored in a project's groups
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return boolean repaired True when entries were rewritten
+function M.fix_renames(bufnr)
+  local root = M.get_current_root(bufnr)
+  if not root then
     return false
   end
 
-  -- Save updated context group
-  local success = store:set(target_path, context_group)
+  local missing = {}
+  local seen = {}
+  local function check(path)
+    if not seen[path] and vim.fn.filereadable(path) ~= 1 and vim.fn.isdirectory(path) ~= 1 then
+      seen[path] = true
+      table.insert(missing, path)
+    end
+  end
 
-  -- Trigger configured callback function
-  local cfg = config.get()
-  if success and cfg.on_context_change then
-    cfg.on_context_change()
+  for _, scope in ipairs(SCOPES) do
+    local store = get_scope_storage(root, scope)
+    for key, context_group in pairs(store and store:all() or {}) do
+      if not groups.is_named_key(key) then
+        check(from_stored_path(key, root))
+      end
+      for _, item in ipairs(context_group) do
+        local value = entries.spec(item)
+        if not groups.is_named_key(value) and not M.is_context_rule(value) then
+          check(from_stored_path(entries.parse(value, root).file, root))
+        end
+      end
+    end
   end
 
-  return success
+  if #missing == 0 then
+    vim.notify("Context groups: no missing files", vim.log.levels.INFO)
+    return false
+  end
+  return M.repair_renames(root, missing, true)
 end
 
----Clear context group
----@param target_bufnr integer|nil Target buffer number
----@return boolean success
-function M.clear_context_group(target_bufnr)
-  local target_path = get_current_filepath(target_bufnr)
-  if not target_path then
-    vim.notify("Cannot clear context: No valid file path found", vim.log.levels.ERROR)
+---Look for renames of the missing files stored in a buffer's group
+---Runs once per buffer when it is first entered; files of included groups are left to :ContextGroupFixRenames
+---@param bufnr integer|nil Buffer number (nil for current buffer)
+---@return boolean repaired True when entries were rewritten
+function M.repair_group_renames(bufnr)
+  local target = resolve_target(bufnr)
+  if not target then
     return false
   end
 
-  local root = M.find_root(target_path)
-  local store = storage.get_storage(root)
+  local missing = {}
+  for _, item in ipairs(read_group(target)) do
+    if not groups.is_named_key(item.value) and not M.is_context_rule(item.value) then
+      local path = from_stored_path(entries.parse(item.value, target.root).file, target.root)
+      if vim.fn.filereadable(path) ~= 1 and vim.fn.isdirectory(path) ~= 1 then
+        table.insert(missing, path)
+      end
+    end
+  end
+  return #missing > 0 and M.repair_renames(target.root, missing)
+end
 
-  -- Remove context group
-  local success = store:delete(target_path)
+---Check whether a stored path no longer exists
+---@param stored string Stored path
+---@param root string Canonical project root
+---@return boolean stale
+local function is_stale_path(stored, root)
+  local path = from_stored_path(stored, root)
+  return vim.fn.filereadable(path) ~= 1 and vim.fn.isdirectory(path) ~= 1
+end
 
-  -- Trigger configured callback function
-  local cfg = config.get()
-  if success and cfg.on_context_change then
-    cfg.on_context_change()
+---Check whether a stored value points at a file, directory or named group that no longer exists
+---Globs and exclusions match whatever exists and are never stale
+---@param value string Stored value
+---@param root string Canonical project root
+---@param group_exists fun(name: string): boolean Whether a named group exists
+---@return boolean stale
+local function is_stale_value(value, root, group_exists)
+  if groups.is_named_key(value) then
+    return not group_exists(groups.name_from_key(value))
+  end
+  local rule = M.get_rule(value)
+  if rule then
+    return not vim.startswith(rule, "!")
+      and vim.endswith(rule, "/")
+      and vim.fn.isdirectory(from_stored_path(rule, root)) ~= 1
+  end
+  return is_stale_path(entries.parse(value, root).file, root)
+end
+
+---@class GarbageReport
+---@field scope "personal"|"shared" Storage scope
+---@field path string Storage file path
+---@field keys string[] Keys of files that no longer exist
+---@field entries {key: string, value: string}[] Entries of files, directories and groups that no longer exist
+
+---Find the stale keys and entries of a store
+---@param store Storage Storage to check
+---@param scope "personal"|"shared" Storage scope
+---@param root string Canonical project root
+---@param group_exists fun(name: string): boolean Whether a named group exists
+---@return GarbageReport item
+---@return table<string, table|false> updates Groups without their stale entries, false for stale keys
+local function find_garbage(store, scope, root, group_exists)
+  local item = { scope = scope, path = store.path, keys = {}, entries = {} }
+  local updates = {}
+  for key, context_group in pairs(store:all()) do
+    if not groups.is_named_key(key) and is_stale_path(key, root) then
+      table.insert(item.keys, key)
+      updates[key] = false
+    else
+      local kept = {}
+      for _, stored in ipairs(context_group) do
+        local value = entries.spec(stored)
+        if is_stale_value(value, root, group_exists) then
+          table.insert(item.entries, { key = key, value = value })
+        else
+          table.insert(kept, vim.deepcopy(stored))
+        end
+      end
+      if #kept ~= #context_group then
+        -- A file's group without entries is removed like an empty group
+        updates[key] = (#kept > 0 or groups.is_named_key(key)) and kept
+      end
+    end
   end
 
-  return success
+  table.sort(item.keys)
+  table.sort(item.entries, function(a, b)
+    return a.key == b.key and a.value < b.value or a.key < b.key
+  end)
+  return item, updates
+end
+
+---Find and optionally prune stale keys and entries of a project
+---Keys of deleted files are removed with their whole group; named groups are kept even when empty.
+---Renamed files are pruned as well, so `fix_renames` should run first to keep them.
+---@param root string Canonical project root
+---@param opts? {dry_run: boolean, scopes: ("personal"|"shared")[]} Options {dry_run: Only report, scopes: Scopes to check}
+---@return GarbageReport[] report One item per storage file
+function M.collect_garbage(root, opts)
+  opts = opts or {}
+  local report = {}
+  local changes = {}
+  local function group_exists(name)
+    return groups.exists(root, name)
+  end
+
+  for _, scope in ipairs(opts.scopes or SCOPES) do
+    local store = get_scope_storage(root, scope)
+    if store then
+      local item, updates = find_garbage(store, scope, root, group_exists)
+      table.insert(report, item)
+      if not opts.dry_run then
+        write_groups(store, scope, updates, changes)
+      end
+    end
+  end
+
+  if #changes > 0 then
+    history.record(root, "Garbage collection", changes)
+    notify_context_change()
+  end
+  return report
+end
+
+---Prune stale keys and entries from the personal storage of every project that still exists
+---Shared storage is committed with the project and only pruned on request. Storage files are read directly,
+---without the watchers and git lookups of opening a project's storage.
+---@return integer keys, integer entries, integer projects Number of pruned keys, entries and affected projects
+function M.collect_all_garbage()
+  local key_count, entry_count, project_count = 0, 0, 0
+  for _, item in ipairs(storage.list_projects()) do
+    local store = vim.fn.isdirectory(item.root) == 1 and storage.peek_storage(item.root, "personal")
+    -- Files with absolute paths are converted when the project is opened, and never written before that
+    if store and not store.legacy and not store.readonly then
+      local shared = storage.peek_storage(item.root, "shared")
+      local function group_exists(name)
+        local key = groups.key(name)
+        return store:get(key) ~= nil or (shared ~= nil and shared:get(key) ~= nil)
+      end
+
+      local changes = {}
+      local garbage, updates = find_garbage(store, "personal", item.root, group_exists)
+      write_groups(store, "personal", updates, changes)
+      if #changes > 0 then
+        key_count = key_count + #garbage.keys
+        entry_count = entry_count + #garbage.entries
+        project_count = project_count + 1
+        history.record(item.root, "Garbage collection", changes)
+      end
+    end
+  end
+  if project_count > 0 then
+    notify_context_change()
+  end
+  return key_count, entry_count, project_count
+end
+
+---Get storage statistics of a project
+---@param root string Canonical project root
+---@return table<string, {path: string, size: integer, groups: integer, named_groups: integer, entries: integer, stale_keys: integer, stale_entries: integer}> stats By scope
+function M.get_storage_stats(root)
+  local stats = {}
+  local garbage = {}
+  for _, item in ipairs(M.collect_garbage(root, { dry_run = true })) do
+    garbage[item.scope] = item
+  end
+
+  for _, scope in ipairs(SCOPES) do
+    local store = get_scope_storage(root, scope)
+    if store then
+      local stat = (vim.uv or vim.loop).fs_stat(store.path)
+      local scope_stats = {
+        path = store.path,
+        size = stat and stat.size or 0,
+        groups = 0,
+        named_groups = 0,
+        entries = 0,
+        stale_keys = #garbage[scope].keys,
+        stale_entries = #garbage[scope].entries,
+      }
+      for key, context_group in pairs(store:all()) do
+        if groups.is_named_key(key) then
+          scope_stats.named_groups = scope_stats.named_groups + 1
+        else
+          scope_stats.groups = scope_stats.groups + 1
+        end
+        scope_stats.entries = scope_stats.entries + #context_group
+      end
+      stats[scope] = scope_stats
+    end
+  end
+  return stats
 end
 
 ---Get context statistics
+---`storage` holds the storage statistics of the buffer's project by scope, see `get_storage_stats`
 ---@param target_bufnr integer|nil Target buffer number
 ---@return table stats Statistics
 function M.get_context_stats(target_bufnr)
   local files = M.get_context_files(target_bufnr)
+  local root = M.get_current_root(target_bufnr)
   local stats = {
     total_files = #files,
     total_lines = 0,
     by_type = {},
     total_size = 0,
+    storage = root and M.get_storage_stats(root) or {},
   }
 
   for _, file in ipairs(files) do
@@ -374,13 +2013,14 @@ end
 ---Add multiple files to context group
 ---@param files string[] Files to add
 ---@param target_bufnr integer|nil Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string, keep_order: boolean} Options {scope: Storage scope (default config.default_scope), group: Named group to add to, keep_order: Append the files in the given order instead of sorting them}
 ---@return table result {success: boolean, added: number, skipped: number, errors: string[]}
-function M.add_multiple_context_files(files, target_bufnr)
+function M.add_multiple_context_files(files, target_bufnr, opts)
   local result = {
     success = false,
     added = 0,
     skipped = 0,
-    errors = {}
+    errors = {},
   }
 
   if #files == 0 then
@@ -388,46 +2028,56 @@ function M.add_multiple_context_files(files, target_bufnr)
     return result
   end
 
-  local target_path = get_current_filepath(target_bufnr)
-  if not target_path then
-    table.insert(result.errors, "No valid file path found")
+  local target = resolve_target(target_bufnr, opts and opts.group)
+  if not target then
+    table.insert(result.errors, "No file open and no named group active")
     return result
   end
 
-  local root = M.find_root(target_path)
-  local store = storage.get_storage(root)
+  local root = target.root
+  local scope = resolve_scope(opts and opts.scope)
+  local store = get_scope_storage(root, scope)
+  local key = get_group_key(target, scope)
+  if not store or not key then
+    table.insert(result.errors, "Shared context groups are unavailable for this file")
+    return result
+  end
 
   -- Get current context group
-  local context_group = store:get(target_path) or {}
+  local context_group = vim.deepcopy(store:get(key) or {})
   local context_set = {}
-  for _, file in ipairs(context_group) do
-    context_set[file] = true
+  for _, item in ipairs(context_group) do
+    context_set[entries.spec(item)] = true
+  end
+
+  -- Append in a stable order rather than the order the file scanner returned
+  files = vim.deepcopy(files)
+  if not (opts and opts.keep_order) then
+    table.sort(files)
   end
 
   -- Process each file
   for _, file in ipairs(files) do
+    local stored_file = to_stored_path(file, root, scope)
     -- Check if file is readable
     if not utils.read_file_content(file) then
       table.insert(result.errors, "File not readable: " .. file)
-    elseif context_set[file] then
+    elseif not stored_file then
+      table.insert(result.errors, "File outside project: " .. file)
+    elseif context_set[stored_file] then
       result.skipped = result.skipped + 1
     else
-      table.insert(context_group, file)
-      context_set[file] = true
+      table.insert(context_group, stored_file)
+      context_set[stored_file] = true
       result.added = result.added + 1
     end
   end
 
   -- Save updated context group if any files were added
   if result.added > 0 then
-    local success = store:set(target_path, context_group)
-    if success then
+    local label = string.format("Add %d files", result.added)
+    if save_group(root, scope, store, key, context_group, label) then
       result.success = true
-      -- Trigger configured callback function
-      local cfg = config.get()
-      if cfg.on_context_change then
-        cfg.on_context_change()
-      end
     else
       table.insert(result.errors, "Failed to save context group")
     end
@@ -0,0 +1,338 @@
+-- lua/context-groups/entries.lua
+-- Line-range and symbol entries: parsing, symbol resolution and extmark tracking
+
+local utils = require("context-groups.utils")
+
+local M = {}
+
+-- Namespace of extmarks that follow range entries in open buffers
+local ns = vim.api.nvim_create_namespace("context-groups-entries")
+
+-- Extmark ids per buffer, keyed by stored entry
+---@type table<integer, table<string, integer>>
+local tracked = {}
+
+-- Treesitter node types that declare a named symbol
+local DECLARATION_TYPES = {
+  "function",
+  "method",
+  "class",
+  "struct",
+  "enum",
+  "interface",
+  "trait",
+  "impl",
+  "type",
+  "module",
+  "const",
+  "declaration",
+  "definition",
+}
+
+---Get the entry of a stored item
+---Items are plain entry strings, or tables `{entry = "...", note = "..."}` when they carry metadata
+---@param item string|table Stored item
+---@return string entry
+function M.spec(item)
+  return type(item) == "table" and item.entry or item
+end
+
+---Get the note of a stored item
+---@param item string|table Stored item
+---@return string|nil note
+function M.note(item)
+  return type(item) == "table" and item.note or nil
+end
+
+---Get the priority of a stored item
+---@param item string|table Stored item
+---@return integer|nil priority
+function M.priority(item)
+  return type(item) == "table" and tonumber(item.priority) or nil
+end
+
+---Build a stored item from an entry and its metadata
+---Items without metadata are stored as plain strings
+---@param entry string Entry
+---@param meta? table Metadata such as `note`
+---@return string|table item
+function M.make(entry, meta)
+  local item = vim.tbl_extend("force", meta or {}, { entry = entry })
+  if vim.tbl_count(item) == 1 then
+    return entry
+  end
+  return item
+end
+
+---Parse a stored entry
+---With a root, a value naming an existing file is that file, so names such as `notes#1.md` keep their `#`
+---@param value string Stored entry: `file`, `file:120-180` or `file#Symbol`
+---@param root? string Directory relative values are checked against
+---@return {file: string, range: integer[]|nil, symbol: string|nil} entry
+function M.parse(value, root)
+  if root then
+    local path = vim.startswith(value, "/") and value or root .. "/" .. value
+    if vim.fn.filereadable(path) == 1 then
+      return { file = value }
+    end
+  end
+
+  local file, first, last = value:match("^(.+):(%d+)%-(%d+)$")
+  if file then
+    return { file = file, range = { tonumber(first), tonumber(last) } }
+  end
+
+  local symbol_file, symbol = value:match("^(.+)#([^/#]+)$")
+  if symbol_file then
+    return { file = symbol_file, symbol = symbol }
+  end
+
+  return { file = value }
+end
+
+---Format an entry for storage or display
+---@param entry {file: string, range: integer[]|nil, symbol: string|nil} Entry
+---@return string value
+function M.format(entry)
+  if entry.range then
+    return string.format("%s:%d-%d", entry.file, entry.range[1], entry.range[2])
+  end
+  if entry.symbol then
+    return entry.file .. "#" .. entry.symbol
+  end
+  return entry.file
+end
+
+---Check whether a name matches the last part of a dotted symbol
+---@param name string Declared name, possibly qualified (`M.setup`, `Server::start`)
+---@param symbol string Requested symbol (`setup`, `Server.start`)
+---@return boolean matches
+local function name_matches(name, symbol)
+  local last = symbol:match("[^%.:]+$")
+  return name == symbol or name:match("[^%.:]+$") == last
+end
+
+---Find a symbol in LSP document symbols
+---@param bufnr integer Loaded buffer
+---@param symbol string Symbol name, optionally dotted (`Server.start`)
+---@return integer[]|nil range 1-based inclusive line range
+local function lsp_symbol_range(bufnr, symbol)
+  local get_clients = vim.lsp.get_clients or vim.lsp.get_active_clients
+  if #get_clients({ bufnr = bufnr }) == 0 then
+    return nil
+  end
+
+  local params = { textDocument = vim.lsp.util.make_text_document_params(bufnr) }
+  local responses = vim.lsp.buf_request_sync(bufnr, "textDocument/documentSymbol", params, 500)
+  local parent = symbol:match("^(.+)[%.:]+[^%.:]+$")
+
+  local function search(symbols, parent_matched)
+    for _, item in ipairs(symbols or {}) do
+      local range = item.range or (item.location and item.location.range)
+      local is_match = name_matches(item.name, symbol)
+        and (not parent or parent_matched or item.containerName == parent)
+      if range and is_match then
+        return { range.start.line + 1, range["end"].line + 1 }
+      end
+      local found = search(item.children, parent_matched or (parent ~= nil and item.name == parent))
+      if found then
+        return found
+      end
+    end
+  end
+
+  for _, response in pairs(responses or {}) do
+    local found = search(response.result, false)
+    if found then
+      return found
+    end
+  end
+  return nil
+end
+
+---Check whether a treesitter node declares a symbol
+---@param node TSNode Node
+---@return boolean
+local function is_declaration(node)
+  local node_type = node:type()
+  for _, kind in ipairs(DECLARATION_TYPES) do
+    if node_type:find(kind, 1, true) then
+      return true
+    end
+  end
+  return false
+end
+
+---Find a symbol with treesitter
+---@param path string File path
+---@param bufnr integer Buffer number or -1 when the file is not loaded
+---@param symbol string Symbol name, optionally dotted (`Server.start`)
+---@return integer[]|nil range 1-based inclusive line range
+local function treesitter_symbol_range(path, bufnr, symbol)
+  local filetype = vim.filetype.match({ filename = path })
+  local lang = filetype and (vim.treesitter.language.get_lang or function(ft)
+    return ft
+  end)(filetype)
+  if not lang then
+    return nil
+  end
+
+  local source, ok, parser
+  if bufnr ~= -1 and vim.api.nvim_buf_is_loaded(bufnr) then
+    source = bufnr
+    ok, parser = pcall(vim.treesitter.get_parser, bufnr, lang)
+  else
+    source = utils.read_file_content(path)
+    if not source then
+      return nil
+    end
+    ok, parser = pcall(vim.treesitter.get_string_parser, source, lang)
+  end
+  if not ok or not parser then
+    return nil
+  end
+
+  local parent = symbol:match("^(.+)[%.:]+[^%.:]+$")
+
+  local function search(node, parent_matched)
+    for child in node:iter_children() do
+      if child:named() then
+        local name_node = is_declaration(child) and child:field("name")[1]
+        local name = name_node and vim.treesitter.get_node_text(name_node, source)
+        -- Receivers and impl blocks name the parent on the declaration line itself
+        local header = name and vim.treesitter.get_node_text(child, source):match("^[^\n]*")
+        if
+          name
+          and name_matches(name, symbol)
+          and (not parent or parent_matched or name == symbol or header:find(parent, 1, true))
+        then
+          local start_row, _, end_row = child:range()
+          return { start_row + 1, end_row + 1 }
+        end
+        local found = search(child, parent_matched or (parent ~= nil and name == parent))
+        if found then
+          return found
+        end
+      end
+    end
+  end
+
+  local tree = parser:parse()[1]
+  return tree and search(tree:root(), false)
+end
+
+---Resolve the line range of a symbol, preferring LSP document symbols of a loaded buffer
+---@param path string Absolute file path
+---@param symbol string Symbol name, optionally dotted (`Server.start`)
+---@return integer[]|nil range 1-based inclusive line range, nil when not found
+function M.resolve_symbol(path, symbol)
+  local bufnr = vim.fn.bufnr(path)
+  if bufnr ~= -1 and vim.api.nvim_buf_is_loaded(bufnr) then
+    local range = lsp_symbol_range(bufnr, symbol)
+    if range then
+      return range
+    end
+  end
+  return treesitter_symbol_range(path, bufnr, symbol)
+end
+
+---Track a range entry with an extmark so it follows edits of an open buffer
+---@param bufnr integer Loaded buffer of the entry's file
+---@param value string Stored entry
+---@param range integer[] 1-based inclusive line range
+function M.track(bufnr, value, range)
+  tracked[bufnr] = tracked[bufnr] or {}
+  if tracked[bufnr][value] then
+    return
+  end
+
+  local line_count = vim.api.nvim_buf_line_count(bufnr)
+  if range[1] > line_count then
+    return
+  end
+
+  tracked[bufnr][value] = vim.api.nvim_buf_set_extmark(bufnr, ns, range[1] - 1, 0, {
+    end_row = math.min(range[2], line_count) - 1,
+    end_col = 0,
+    end_right_gravity = true,
+  })
+end
+
+---Get the current range of a tracked entry
+---@param path string Absolute file path
+---@param value string Stored entry
+---@return integer[]|nil range 1-based inclusive line range, nil when not tracked
+function M.get_tracked_range(path, value)
+  local bufnr = vim.fn.bufnr(path)
+  local id = tracked[bufnr] and tracked[bufnr][value]
+  if not id then
+    return nil
+  end
+
+  local mark = vim.api.nvim_buf_get_extmark_by_id(bufnr, ns, id, { details = true })
+  if not mark[1] then
+    return nil
+  end
+  return { mark[1] + 1, math.max(mark[3].end_row or mark[1], mark[1]) + 1 }
+end
+
+---Collect tracked entries of a buffer whose range moved since they were stored
+---Moved entries are re-tracked under their new stored value
+---@param bufnr integer Buffer number
+---@return {old: string, new: string}[] moved
+function M.take_moved(bufnr)
+  local moved = {}
+  local path = vim.api.nvim_buf_get_name(bufnr)
+
+  for value in pairs(tracked[bufnr] or {}) do
+    local entry = M.parse(value)
+    local range = M.get_tracked_range(path, value)
+    if range and entry.range and (range[1] ~= entry.range[1] or range[2] ~= entry.range[2]) then
+      entry.range = range
+      table.insert(moved, { old = value, new = M.format(entry) })
+    end
+  end
+
+  for _, item in ipairs(moved) do
+    tracked[bufnr][item.new] = tracked[bufnr][item.old]
+    tracked[bufnr][item.old] = nil
+  end
+
+  return moved
+end
+
+---Forget the extmarks of a buffer
+---@param bufnr integer Buffer number
+function M.untrack(bufnr)
+  tracked[bufnr] = nil
+  pcall(vim.api.nvim_buf_clear_namespace, bufnr, ns, 0, -1)
+end
+
+---Read lines of a file, preferring the contents of a loaded buffer
+---@param path string Absolute file path
+---@return string[]|nil lines
+function M.read_lines(path)
+  local bufnr = vim.fn.bufnr(path)
+  if bufnr ~= -1 and vim.api.nvim_buf_is_loaded(bufnr) then
+    return vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+  end
+
+  local content = utils.read_file_content(path)
+  return content and vim.split(content, "\n") or nil
+end
+
+---Render a line range with line numbers
+---@param lines string[] File lines
+---@param range integer[] 1-based inclusive line range
+---@return string content
+function M.render_range(lines, range)
+  local last = math.min(range[2], #lines)
+  local width = #tostring(last)
+  local rendered = {}
+  for line_nr = range[1], last do
+    table.insert(rendered, string.format("%" .. width .. "d  %s", line_nr, lines[line_nr]))
+  end
+  return table.concat(rendered, "\n")
+end
+
+return M
@@ -44,6 +44,7 @@ local function generate_tree_structure(root, paths, depth)
 
   -- Preprocess each path to ensure all necessary parent directories are included
   for _, file_path in ipairs(paths) do
+    file_path = require("context-groups.core").get_rule(file_path) or file_path
     -- Check if file path should be excluded
     if not should_exclude(file_path, exclude_patterns) then
       local current_path = ""
@@ -198,7 +199,27 @@ local function process_paths(paths)
     -- Check if path should be excluded
     if not should_exclude(path, exclude_patterns) then
       local full_path = root .. "/" .. path
-      if vim.fn.isdirectory(full_path) == 1 then
+      -- Paths are globs when marked as rules, or when no such file exists, so `pages/[id].tsx` stays a file
+      local core = require("context-groups.core")
+      local glob = core.get_rule(path)
+      if not glob and vim.fn.filereadable(full_path) == 0 and vim.fn.isdirectory(full_path) == 0 then
+        glob = path:find("[%*%?%[]") and path
+      end
+      if glob then
+        -- Globs are evaluated against the files present at export time
+        for _, file in ipairs(core.get_directory_files(root)) do
+          local rel_path = file:sub(#root + 2)
+          if utils.glob_match(glob, rel_path) and not should_exclude(rel_path, exclude_patterns) then
+            local content = utils.read_file_content(file)
+            if content then
+              table.insert(contents, {
+                path = rel_path,
+                content = content,
+              })
+            end
+          end
+        end
+      elseif vim.fn.isdirectory(full_path) == 1 then
         process_directory(full_path)
       else
         local content = utils.read_file_content(full_path)
@@ -215,8 +236,44 @@ local function process_paths(paths)
   return contents
 end
 
+---Get the notes, priorities and positions of the current context group by relative path
+---@param bufnr? integer Buffer whose context group holds the notes
+---@return table<string, string> notes, table<string, number> priorities, table<string, integer> positions
+local function get_context_meta(bufnr)
+  local core = require("context-groups.core")
+  local notes = {}
+  local priorities = {}
+  local positions = {}
+
+  -- Exports without a file open or an active named group have no context group
+  local has_file = vim.fn.filereadable(vim.api.nvim_buf_get_name(bufnr or 0)) == 1
+  if not has_file and not core.get_active_group(bufnr) then
+    return notes, priorities, positions
+  end
+
+  for i, entry in ipairs(core.get_context_entries(bufnr)) do
+    local rel_path = core.get_relative_path(entry.path)
+    positions[rel_path] = positions[rel_path] or i
+    if entry.note then
+      notes[rel_path] = notes[rel_path] and (notes[rel_path] .. "; " .. entry.note) or entry.note
+    end
+    if entry.priority then
+      priorities[rel_path] = math.max(priorities[rel_path] or entry.priority, entry.priority)
+    end
+  end
+  return notes, priorities, positions
+end
+
+---Escape a value for an XML-style attribute
+---@param value string Value
+---@return string escaped
+local function escape_attribute(value)
+  return (value:gsub("&", "&amp;"):gsub('"', "&quot;"):gsub("<", "&lt;"):gsub(">", "&gt;"))
+end
+
 ---Export project contents
----@param opts? table Export options
+---Files with a note in the current context group carry it as a `note` attribute
+---@param opts? table Export options {paths: string[], notes: table<string, string>, bufnr: integer, size_budget: integer|false}
 ---@return table? result Export result
 function M.export_contents(opts)
   opts = opts or {}
@@ -253,9 +310,29 @@ function M.export_contents(opts)
   table.insert(result, "All project files:")
   table.insert(result, "<code_base>\n")
 
+  local notes, priorities, positions = get_context_meta(opts.bufnr)
+  notes = opts.notes or notes
+
+  -- Files follow the order of the context group, other files come after them in the order they were found
   local contents = process_paths(paths)
+  for i, file in ipairs(contents) do
+    file.priority = priorities[file.path]
+    file.position = positions[file.path] or #contents + i
+  end
+  table.sort(contents, function(a, b)
+    return a.position < b.position
+  end)
+
+  -- Files beyond the size budget are dropped lowest priority first
+  contents = require("context-groups.core").apply_size_budget(contents, opts.size_budget)
+
   for _, file in ipairs(contents) do
-    table.insert(result, string.format('<code path="%s">\n', file.path))
+    local note = notes[file.path]
+    if note then
+      table.insert(result, string.format('<code path="%s" note="%s">\n', file.path, escape_attribute(note)))
+    else
+      table.insert(result, string.format('<code path="%s">\n', file.path))
+    end
     table.insert(result, file.content)
     table.insert(result, "</code>\n")
   end
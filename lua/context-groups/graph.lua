@@ -0,0 +1,157 @@
+-- lua/context-groups/graph.lua
+-- Project import graph, cached per project and refreshed as files are written
+
+local config = require("context-groups.config")
+local lsp = require("context-groups.lsp")
+local project = require("context-groups.project")
+local utils = require("context-groups.utils")
+
+local M = {}
+
+local uv = vim.uv or vim.loop
+
+---@class GraphNode
+---@field path string Absolute path of the dependency
+---@field depth integer Import hops from the start file
+---@field imported_by string Absolute path of the file importing it on the shortest path
+---@field import string Import as written in that file
+
+-- Project files imported by every file read so far, by project root and file, with the file's mtime at reading
+---@type table<string, table<string, {mtime: string|nil, imports: {name: string, path: string}[]}>>
+local cache = {}
+
+---Get the modification time of a file
+---@param path string File path
+---@return string|nil mtime Nil when the file does not exist
+local function get_mtime(path)
+  local stat = uv.fs_stat(path)
+  return stat and string.format("%d.%d", stat.mtime.sec, stat.mtime.nsec) or nil
+end
+
+---Read the project files a file imports
+---@param path string Canonical file path
+---@param root string Canonical project root
+---@return {name: string, path: string}[] imports
+local function read_imports(path, root)
+  local imports = {}
+  for _, import in ipairs(lsp.get_file_imports(path, { all = true })) do
+    if import.kind == "project" and import.path then
+      -- Package directories stand for the source files their language handler picks
+      local files = vim.list_extend(vim.deepcopy(import.files or { import.path }), import.related or {})
+      for _, file in ipairs(files) do
+        if file ~= path and vim.startswith(file, root .. "/") then
+          table.insert(imports, { name = import.name, path = file })
+        end
+      end
+    end
+  end
+  return imports
+end
+
+---Get the project files a file imports, reading the file again only when it changed since the last read
+---@param path string Canonical file path
+---@param root string Canonical project root
+---@return {name: string, path: string}[] imports
+function M.get_imports(path, root)
+  cache[root] = cache[root] or {}
+  local mtime = get_mtime(path)
+  local cached = cache[root][path]
+  if not cached or cached.mtime ~= mtime then
+    cached = { mtime = mtime, imports = read_imports(path, root) }
+    cache[root][path] = cached
+  end
+  return cached.imports
+end
+
+---Refresh a written buffer in the graph of its project
+---Only files already in the graph are read again; others are read when a closure first reaches them
+---@param bufnr integer Buffer number
+function M.refresh(bufnr)
+  local name = vim.api.nvim_buf_get_name(bufnr)
+  if name == "" then
+    return
+  end
+
+  local path = utils.canonical_path(name)
+  local root = utils.canonical_path(project.find_root(path))
+  if cache[root] and cache[root][path] then
+    cache[root][path] = { mtime = get_mtime(path), imports = read_imports(path, root) }
+  end
+end
+
+---Drop the cached graph of a project, or of every project
+---@param root? string Canonical project root
+function M.clear(root)
+  if root then
+    cache[root] = nil
+  else
+    cache = {}
+  end
+end
+
+---Collect the project files a file depends on, following imports breadth first
+---@param path string File path
+---@param opts? {depth: integer, max_files: integer} Options (default config.import_graph)
+---@return GraphNode[] nodes Dependencies nearest first, without the file itself
+---@return boolean truncated Whether max_files cut the closure short
+function M.closure(path, opts)
+  local graph_config = config.get().import_graph or {}
+  local depth = opts and opts.depth or graph_config.depth or 2
+  local max_files = opts and opts.max_files or graph_config.max_files or 200
+
+  path = utils.canonical_path(path)
+  local root = utils.canonical_path(project.find_root(path))
+  local nodes = {}
+  local seen = { [path] = true }
+  local queue = { { path = path, depth = 0 } }
+
+  local index = 1
+  while queue[index] do
+    local node = queue[index]
+    index = index + 1
+    if node.depth < depth then
+      for _, import in ipairs(M.get_imports(node.path, root)) do
+        if not seen[import.path] then
+          if #nodes >= max_files then
+            return nodes, true
+          end
+          seen[import.path] = true
+          local dependency = {
+            path = import.path,
+            depth = node.depth + 1,
+            imported_by = node.path,
+            import = import.name,
+          }
+          table.insert(nodes, dependency)
+          table.insert(queue, dependency)
+        end
+      end
+    end
+  end
+
+  return nodes, false
+end
+
+---Order closure nodes as a tree, each file directly below the file importing it
+---@param path string Canonical path of the start file
+---@param nodes GraphNode[] Closure of the start file
+---@return GraphNode[] nodes Same nodes in depth-first order
+function M.tree_order(path, nodes)
+  local children = {}
+  for _, node in ipairs(nodes) do
+    children[node.imported_by] = children[node.imported_by] or {}
+    table.insert(children[node.imported_by], node)
+  end
+
+  local ordered = {}
+  local function visit(parent)
+    for _, node in ipairs(children[parent] or {}) do
+      table.insert(ordered, node)
+      visit(node.path)
+    end
+  end
+  visit(path)
+  return ordered
+end
+
+return M
@@ -0,0 +1,265 @@
+-- lua/context-groups/groups.lua
+-- Named context groups that are independent of buffers
+
+local config = require("context-groups.config")
+local history = require("context-groups.history")
+local storage = require("context-groups.storage")
+
+local M = {}
+
+-- Storage keys of named groups start with this prefix; file keys are paths
+M.PREFIX = storage.NAMED_GROUP_PREFIX
+
+-- Active named group per project root
+---@type table<string, string>
+local active_groups = {}
+
+---Trigger configured callback function
+local function notify_context_change()
+  local cfg = config.get()
+  if cfg.on_context_change then
+    cfg.on_context_change()
+  end
+end
+
+---Get the stores that may hold named groups for a project
+---@param root string Canonical project root
+---@return {scope: "personal"|"shared", store: Storage}[] stores
+local function get_stores(root)
+  local stores = { { scope = "personal", store = storage.get_storage(root) } }
+  local shared = storage.get_shared_storage(root)
+  if shared then
+    table.insert(stores, { scope = "shared", store = shared })
+  end
+  return stores
+end
+
+---Save or delete a group in one store, adding the saved change to an undo history entry
+---@param item {scope: "personal"|"shared", store: Storage} Store and its scope
+---@param key string Storage key
+---@param value table|nil New group, nil to delete it
+---@param changes HistoryChange[] Changes of the edit, appended to
+---@return boolean success
+local function write_group(item, key, value, changes)
+  local before = vim.deepcopy(item.store:get(key))
+  local saved
+  if value then
+    saved = item.store:set(key, value)
+  else
+    saved = item.store:delete(key)
+  end
+  if saved then
+    local change = { scope = item.scope, branch = item.store.meta.branch, key = key, before = before, after = value }
+    table.insert(changes, change)
+  end
+  return saved
+end
+
+---Get the storage key of a named group
+---@param name string Group name
+---@return string key
+function M.key(name)
+  return M.PREFIX .. name
+end
+
+---Get the label a named group is shown with
+---@param name string Group name
+---@return string label
+function M.label(name)
+  return "@" .. name
+end
+
+---Check whether a storage key belongs to a named group
+---@param key string Storage key
+---@return boolean
+function M.is_named_key(key)
+  return vim.startswith(key, M.PREFIX)
+end
+
+---Get the group name from a storage key
+---@param key string Storage key
+---@return string name
+function M.name_from_key(key)
+  return key:sub(#M.PREFIX + 1)
+end
+
+---Validate a group name
+---@param name string|nil Group name
+---@return boolean valid
+function M.is_valid_name(name)
+  return type(name) == "string" and name:match("^[%w][%w%._%-]*$") ~= nil
+end
+
+---List named groups of a project
+---@param root string Canonical project root
+---@return {name: string, scopes: string[], count: number, active: boolean}[] groups Sorted by name
+function M.list(root)
+  local by_name = {}
+
+  for _, item in ipairs(get_stores(root)) do
+    for key, group in pairs(item.store:all()) do
+      if M.is_named_key(key) then
+        local name = M.name_from_key(key)
+        by_name[name] = by_name[name] or { name = name, scopes = {}, count = 0, active = active_groups[root] == name }
+        table.insert(by_name[name].scopes, item.scope)
+        by_name[name].count = by_name[name].count + #group
+      end
+    end
+  end
+
+  local result = vim.tbl_values(by_name)
+  table.sort(result, function(a, b)
+    return a.name < b.name
+  end)
+  return result
+end
+
+---Check whether a named group exists in any scope
+---@param root string Canonical project root
+---@param name string Group name
+---@return boolean exists
+function M.exists(root, name)
+  for _, item in ipairs(get_stores(root)) do
+    if item.store:get(M.key(name)) ~= nil then
+      return true
+    end
+  end
+  return false
+end
+
+---Create an empty named group
+---@param root string Canonical project root
+---@param name string Group name
+---@param opts? {scope: "personal"|"shared"} Options {scope: Storage scope (default config.default_scope)}
+---@return boolean success
+function M.create(root, name, opts)
+  if not M.is_valid_name(name) then
+    vim.notify("Invalid group name: " .. tostring(name), vim.log.levels.ERROR)
+    return false
+  end
+
+  if M.exists(root, name) then
+    vim.notify("Context group already exists: " .. name, vim.log.levels.WARN)
+    return false
+  end
+
+  local scope = opts and opts.scope or config.get().default_scope or "personal"
+  local store = scope == "shared" and storage.get_shared_storage(root) or storage.get_storage(root)
+  if not store then
+    vim.notify("Cannot create group: Shared context groups are disabled", vim.log.levels.ERROR)
+    return false
+  end
+
+  local changes = {}
+  local success = write_group({ scope = scope, store = store }, M.key(name), {}, changes)
+  history.record(root, "Create " .. M.label(name), changes)
+  if success then
+    notify_context_change()
+  end
+  return success
+end
+
+---Rename a named group in every scope
+---@param root string Canonical project root
+---@param old_name string Current group name
+---@param new_name string New group name
+---@return boolean success
+function M.rename(root, old_name, new_name)
+  if not M.is_valid_name(new_name) then
+    vim.notify("Invalid group name: " .. tostring(new_name), vim.log.levels.ERROR)
+    return false
+  end
+
+  if not M.exists(root, old_name) then
+    vim.notify("Context group not found: " .. old_name, vim.log.levels.ERROR)
+    return false
+  end
+
+  if M.exists(root, new_name) then
+    vim.notify("Context group already exists: " .. new_name, vim.log.levels.ERROR)
+    return false
+  end
+
+  local success = true
+  local changes = {}
+  for _, item in ipairs(get_stores(root)) do
+    local group = item.store:get(M.key(old_name))
+    if group ~= nil then
+      success = write_group(item, M.key(new_name), vim.deepcopy(group), changes)
+        and write_group(item, M.key(old_name), nil, changes)
+        and success
+    end
+  end
+  history.record(root, string.format("Rename %s to %s", M.label(old_name), M.label(new_name)), changes)
+
+  if active_groups[root] == old_name then
+    active_groups[root] = new_name
+  end
+
+  if success then
+    notify_context_change()
+  end
+  return success
+end
+
+---Delete a named group from every scope
+---@param root string Canonical project root
+---@param name string Group name
+---@return boolean success
+function M.delete(root, name)
+  if not M.exists(root, name) then
+    vim.notify("Context group not found: " .. name, vim.log.levels.ERROR)
+    return false
+  end
+
+  local success = true
+  local changes = {}
+  for _, item in ipairs(get_stores(root)) do
+    if item.store:get(M.key(name)) ~= nil then
+      success = write_group(item, M.key(name), nil, changes) and success
+    end
+  end
+  history.record(root, "Delete " .. M.label(name), changes)
+
+  if active_groups[root] == name then
+    active_groups[root] = nil
+  end
+
+  if success then
+    notify_context_change()
+  end
+  return success
+end
+
+---Activate a named group so every buffer of the project uses it
+---@param root string Canonical project root
+---@param name string Group name
+---@return boolean success
+function M.activate(root, name)
+  if not M.exists(root, name) then
+    vim.notify("Context group not found: " .. name, vim.log.levels.ERROR)
+    return false
+  end
+
+  active_groups[root] = name
+  notify_context_change()
+  return true
+end
+
+---Deactivate the active named group so buffers use their own groups again
+---@param root string Canonical project root
+function M.deactivate(root)
+  if active_groups[root] then
+    active_groups[root] = nil
+    notify_context_change()
+  end
+end
+
+---Get the active named group of a project
+---@param root string Canonical project root
+---@return string|nil name
+function M.get_active(root)
+  return active_groups[root]
+end
+
+return M
@@ -0,0 +1,160 @@
+-- lua/context-groups/history.lua
+-- Bounded per-project undo/redo history of context group edits
+
+local config = require("context-groups.config")
+local storage = require("context-groups.storage")
+
+local M = {}
+
+---@class HistoryChange
+---@field scope "personal"|"shared" Storage scope of the group
+---@field branch string|nil Branch of the personal groups that were edited, nil for the default branch
+---@field key string Storage key of the group
+---@field before table|nil Group before the edit, nil when it did not exist
+---@field after table|nil Group after the edit, nil when it was deleted
+
+---@class HistoryEntry
+---@field label string Description of the edit
+---@field time integer Time of the edit
+---@field changes HistoryChange[] Groups changed by the edit
+
+-- History per project root; position is the number of applied entries
+---@type table<string, {entries: HistoryEntry[], position: integer}>
+local histories = {}
+
+---Trigger configured callback function
+local function notify_context_change()
+  local cfg = config.get()
+  if cfg.on_context_change then
+    cfg.on_context_change()
+  end
+end
+
+---Get the history of a project
+---@param root string Canonical project root
+---@return {entries: HistoryEntry[], position: integer} history
+local function get_history(root)
+  histories[root] = histories[root] or { entries = {}, position = 0 }
+  return histories[root]
+end
+
+---Record an edit; entries that were undone can no longer be redone
+---@param root string Canonical project root
+---@param label string Description of the edit
+---@param changes HistoryChange[] Groups changed by the edit
+function M.record(root, label, changes)
+  if #changes == 0 then
+    return
+  end
+
+  local history = get_history(root)
+  for i = #history.entries, history.position + 1, -1 do
+    table.remove(history.entries, i)
+  end
+
+  table.insert(history.entries, { label = label, time = os.time(), changes = vim.deepcopy(changes) })
+
+  local limit = config.get().history_size or 100
+  while #history.entries > limit do
+    table.remove(history.entries, 1)
+  end
+  history.position = #history.entries
+end
+
+---Write one side of an entry's changes back to storage
+---@param root string Canonical project root
+---@param entry HistoryEntry History entry
+---@param side "before"|"after" State to restore
+---@return boolean success
+local function apply(root, entry, side)
+  local success = true
+  for _, change in ipairs(entry.changes) do
+    local store = change.scope == "shared" and storage.get_shared_storage(root)
+      or storage.get_branch_storage(root, change.branch)
+    local value = change[side]
+    if not store then
+      success = false
+    elseif value == nil then
+      success = store:delete(change.key) and success
+    else
+      success = store:set(change.key, vim.deepcopy(value)) and success
+    end
+  end
+  return success
+end
+
+---Undo the last applied edit
+---The position only moves when the edit was written, so a failed undo can be retried
+---@param root string Canonical project root
+---@return HistoryEntry|nil entry Entry to undo, nil when there is nothing to undo
+---@return boolean success Whether the entry was undone
+function M.undo(root)
+  local history = get_history(root)
+  local entry = history.entries[history.position]
+  if not entry then
+    return nil, false
+  end
+
+  local success = apply(root, entry, "before")
+  if success then
+    history.position = history.position - 1
+  end
+  notify_context_change()
+  return entry, success
+end
+
+---Redo the last undone edit
+---@param root string Canonical project root
+---@return HistoryEntry|nil entry Entry to redo, nil when there is nothing to redo
+---@return boolean success Whether the entry was redone
+function M.redo(root)
+  local history = get_history(root)
+  local entry = history.entries[history.position + 1]
+  if not entry then
+    return nil, false
+  end
+
+  local success = apply(root, entry, "after")
+  if success then
+    history.position = history.position + 1
+  end
+  notify_context_change()
+  return entry, success
+end
+
+---Restore the state right after an entry, undoing or redoing every edit in between
+---Stops at the first edit that cannot be written, leaving the position there
+---@param root string Canonical project root
+---@param position integer Number of entries to keep applied (0 restores the state before the first entry)
+---@return boolean success Whether the requested state was reached
+function M.restore(root, position)
+  local history = get_history(root)
+  position = math.max(0, math.min(position, #history.entries))
+
+  local success = true
+  while success and history.position > position do
+    success = apply(root, history.entries[history.position], "before")
+    if success then
+      history.position = history.position - 1
+    end
+  end
+  while success and history.position < position do
+    success = apply(root, history.entries[history.position + 1], "after")
+    if success then
+      history.position = history.position + 1
+    end
+  end
+
+  notify_context_change()
+  return success
+end
+
+---List the history of a project, oldest first
+---@param root string Canonical project root
+---@return HistoryEntry[] entries, integer position Number of applied entries
+function M.list(root)
+  local history = get_history(root)
+  return history.entries, history.position
+end
+
+return M
@@ -16,29 +16,92 @@ function M.setup(opts)
 
   -- Setup UI components
   ui.setup()
+
+  -- Prune stale storage once startup is done
+  if config.get().gc_on_setup then
+    vim.schedule(function()
+      local keys, items, projects = core.collect_all_garbage()
+      if keys + items > 0 then
+        vim.notify(
+          string.format("Context groups: pruned %d stale keys and %d entries in %d projects", keys, items, projects)
+        )
+      end
+    end)
+  end
 end
 
 -- Add file to context group
 ---@param file string File path
 ---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared"} Options
+---@return boolean success
+function M.add_context_file(file, bufnr, opts)
+  return core.add_context_file(file, bufnr, opts)
+end
+
+-- Add glob, directory or exclusion rule to context group
+---@param rule string Rule such as "src/api/**/*.go", "src/api/" or "!**/*_test.go"
+---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options
+---@return boolean success
+function M.add_context_rule(rule, bufnr, opts)
+  return core.add_context_rule(rule, bufnr, opts)
+end
+
+-- Remove rule from context group
+---@param rule string Stored rule
+---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options
+---@return boolean success
+function M.remove_context_rule(rule, bufnr, opts)
+  return core.remove_context_rule(rule, bufnr, opts)
+end
+
+-- Set or clear the note of a context group entry
+---@param entry string Stored entry or rule
+---@param note string|nil Note, nil or empty to clear it
+---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options
+---@return boolean success
+function M.set_entry_note(entry, note, bufnr, opts)
+  return core.set_entry_note(entry, note, bufnr, opts)
+end
+
+-- Set or clear the priority of a context group entry
+---@param entry string Stored entry or rule
+---@param priority integer|nil Priority, nil or 0 for the default
+---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options
+---@return boolean success
+function M.set_entry_priority(entry, priority, bufnr, opts)
+  return core.set_entry_priority(entry, priority, bufnr, opts)
+end
+
+-- Move a context group entry up, down or to the top of its group
+---@param entry string Stored entry or rule
+---@param direction "up"|"down"|"top" Where to move it
+---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared", group: string} Options
 ---@return boolean success
-function M.add_context_file(file, bufnr)
-  return core.add_context_file(file, bufnr)
+function M.move_entry(entry, direction, bufnr, opts)
+  return core.move_entry(entry, direction, bufnr, opts)
 end
 
 -- Remove file from context group
 ---@param file string File path
 ---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared"} Options
 ---@return boolean success
-function M.remove_context_file(file, bufnr)
-  return core.remove_context_file(file, bufnr)
+function M.remove_context_file(file, bufnr, opts)
+  return core.remove_context_file(file, bufnr, opts)
 end
 
 -- Get context files
 ---@param bufnr? number Target buffer number
+---@param opts? {relative: boolean, group: string} Options
 ---@return string[] files List of file paths
-function M.get_context_files(bufnr)
-  return core.get_context_files(bufnr)
+function M.get_context_files(bufnr, opts)
+  return core.get_context_files(bufnr, opts)
 end
 
 -- Get context contents
@@ -50,9 +113,115 @@ end
 
 -- Clear context group
 ---@param bufnr? number Target buffer number
+---@param opts? {scope: "personal"|"shared"|"all"} Options
+---@return boolean|nil success Nil when the group was already empty
+function M.clear_context_group(bufnr, opts)
+  return core.clear_context_group(bufnr, opts)
+end
+
+-- Rewrite context groups after a file or directory was renamed outside of git and the supported explorers
+---@param old string Path before the rename
+---@param new string Path after the rename
+---@return boolean success
+function M.rename_file(old, new)
+  return core.rename_file(old, new)
+end
+
+-- Get the test or implementation files paired with the current file
+---@return string[] files Absolute paths of existing paired files
+function M.get_paired_files()
+  return core.get_paired_files()
+end
+
+-- Save the current context group, or every group, to a portable JSON/YAML bundle
+---@param file string Bundle file, YAML for `.yaml`/`.yml` and JSON otherwise
+---@param opts? {group: string, all: boolean} Options {group: Named group to save, all: Save every group}
+---@return boolean success
+function M.save_bundle(file, opts)
+  return core.save_bundle(file, nil, opts)
+end
+
+-- Load context groups from a bundle into the current project
+---@param file string Bundle file
+---@param opts? {policy: "merge"|"replace"|"skip", scope: "personal"|"shared"} Options
+---@return boolean success
+function M.load_bundle(file, opts)
+  return core.load_bundle(file, nil, opts)
+end
+
+-- Copy personal context groups from another branch into the checked out branch's groups
+---@param branch string Branch to copy from
+---@param opts? {group: string, all: boolean} Options {group: Named group to copy, all: Copy every group}
+---@return boolean success
+function M.copy_from_branch(branch, opts)
+  return core.copy_from_branch(branch, nil, opts)
+end
+
+-- Prune keys and entries of deleted files from the current project's context groups
+---@param opts? {dry_run: boolean, scopes: ("personal"|"shared")[]} Options
+---@return GarbageReport[] report One item per storage file
+function M.collect_garbage(opts)
+  local root = core.get_current_root()
+  return root and core.collect_garbage(root, opts) or {}
+end
+
+-- Undo the last context group edit of the current project
+---@return boolean success
+function M.undo()
+  return core.undo()
+end
+
+-- Redo the last undone context group edit of the current project
+---@return boolean success
+function M.redo()
+  return core.redo()
+end
+
+-- List every project with stored context groups
+---@return {root: string, path: string, groups: number}[] projects
+function M.list_projects()
+  return require("context-groups.storage").list_projects()
+end
+
+-- Create a named context group in the current project
+---@param name string Group name
+---@param opts? {scope: "personal"|"shared"} Options
+---@return boolean success
+function M.create_group(name, opts)
+  return require("context-groups.groups").create(core.get_current_root(), name, opts)
+end
+
+-- Rename a named context group in the current project
+---@param old_name string Current group name
+---@param new_name string New group name
 ---@return boolean success
-function M.clear_context_group(bufnr)
-  return core.clear_context_group(bufnr)
+function M.rename_group(old_name, new_name)
+  return require("context-groups.groups").rename(core.get_current_root(), old_name, new_name)
+end
+
+-- Delete a named context group in the current project
+---@param name string Group name
+---@return boolean success
+function M.delete_group(name)
+  return require("context-groups.groups").delete(core.get_current_root(), name)
+end
+
+-- Activate a named context group for every buffer of the current project
+---@param name string Group name
+---@return boolean success
+function M.activate_group(name)
+  return require("context-groups.groups").activate(core.get_current_root(), name)
+end
+
+-- Deactivate the active named context group of the current project
+function M.deactivate_group()
+  require("context-groups.groups").deactivate(core.get_current_root())
+end
+
+-- List named context groups of the current project
+---@return {name: string, scopes: string[], count: number, active: boolean}[] groups
+function M.list_groups()
+  return require("context-groups.groups").list(core.get_current_root())
 end
 
 -- Show context group picker
@@ -65,6 +234,27 @@ function M.show_imports_picker()
   require("context-groups.picker").show_imports_picker()
 end
 
+-- Get the imports of a buffer, resolved to files
+---@param bufnr? number Buffer number (default current buffer)
+---@return ImportInfo[] imports
+function M.get_imports(bufnr)
+  return require("context-groups.lsp").get_imports(bufnr)
+end
+
+-- Get the project files the current file depends on through its imports
+---@param opts? {depth: integer} Options {depth: Maximum import hops}
+---@return GraphNode[] nodes Dependencies nearest first, with the file importing each
+function M.get_dependencies(opts)
+  return (core.get_dependencies(nil, opts))
+end
+
+-- Add the project files the current file depends on to its context group
+---@param opts? {depth: integer, files: string[], scope: "personal"|"shared"} Options
+---@return boolean success
+function M.add_dependencies(opts)
+  return core.add_dependencies(nil, opts)
+end
+
 -- Export codebase contents
 ---@param opts table Export options
 ---@return table? Export result
@@ -80,10 +270,11 @@ function M.call_code2prompt_current()
 end
 
 -- Call code2prompt on all open buffers
+---@param opts? {group: string} Options {group: Use this named group instead of the open buffers}
 ---@return boolean success
-function M.call_code2prompt()
+function M.call_code2prompt(opts)
   -- Call code2prompt module to generate prompt
-  return require("context-groups.code2prompt").generate_prompt()
+  return require("context-groups.code2prompt").generate_prompt(opts)
 end
 
 -- Get LSP diagnostics for current buffer
@@ -0,0 +1,303 @@
+-- lua/context-groups/lsp/c.lua
+-- C/C++ include resolution through the include paths of compile_commands.json and the compiler's system dirs
+
+local project = require("context-groups.project")
+local utils = require("context-groups.utils")
+
+---@type ImportHandler
+local M = {}
+
+-- Extensions of the source files implementing a header
+local SOURCE_EXTENSIONS = { "cc", "cpp", "cxx", "c", "m", "mm" }
+
+---@class CompileFlags
+---@field compiler string|nil Compiler executable
+---@field quote string[] Directories searched for quoted includes only (-iquote)
+---@field include string[] Directories searched for all includes (-I)
+---@field system string[] System directories (-isystem, -idirafter)
+
+---@class CompileDatabase
+---@field files table<string, CompileFlags> Flags by canonical source file
+---@field all CompileFlags Union of the flags of every file, for headers and files missing from the database
+---@field sources table<string, string[]> Canonical source files by file name without extension
+
+-- Compilation databases by path, with the mtime they were read at
+---@type table<string, {mtime: integer, database: CompileDatabase}>
+local databases = {}
+
+-- Default system include directories by compiler and language
+---@type table<string, string[]>
+local system_dirs = {}
+
+---Split a shell command line into arguments, honoring quotes and backslash escapes
+---@param command string Command line
+---@return string[] args
+local function split_command(command)
+  local args = {}
+  local current, quote, has_arg = {}, nil, false
+  local i = 1
+  while i <= #command do
+    local char = command:sub(i, i)
+    if char == "\\" and quote ~= "'" then
+      i = i + 1
+      table.insert(current, command:sub(i, i))
+      has_arg = true
+    elseif quote then
+      if char == quote then
+        quote = nil
+      else
+        table.insert(current, char)
+      end
+    elseif char == '"' or char == "'" then
+      quote, has_arg = char, true
+    elseif char:match("%s") then
+      if has_arg then
+        table.insert(args, table.concat(current))
+        current, has_arg = {}, false
+      end
+    else
+      table.insert(current, char)
+      has_arg = true
+    end
+    i = i + 1
+  end
+  if has_arg then
+    table.insert(args, table.concat(current))
+  end
+  return args
+end
+
+-- Directories each list already holds, so adding stays constant time for databases with thousands of commands
+---@type table<string[], table<string, boolean>>
+local list_dirs = setmetatable({}, { __mode = "k" })
+
+---Add a directory to a list once
+---@param list string[] Directories
+---@param dir string Directory
+---@param canonical table<string, string> Canonical paths by path; commands repeat the same flags for every file
+local function add_dir(list, dir, canonical)
+  canonical[dir] = canonical[dir] or utils.canonical_path(dir)
+  dir = canonical[dir]
+
+  list_dirs[list] = list_dirs[list] or {}
+  if not list_dirs[list][dir] then
+    list_dirs[list][dir] = true
+    table.insert(list, dir)
+  end
+end
+
+---Extract the include directories of a compile command
+---@param args string[] Compiler arguments
+---@param directory string Working directory of the command
+---@param flags CompileFlags Flags to add to
+---@param canonical table<string, string> Canonical paths by path, shared by the commands of a database
+local function parse_flags(args, directory, flags, canonical)
+  flags.compiler = flags.compiler or args[1]
+  local options = { ["-I"] = "include", ["-iquote"] = "quote", ["-isystem"] = "system", ["-idirafter"] = "system" }
+
+  local i = 2
+  while i <= #args do
+    local arg = args[i]
+    for option, field in pairs(options) do
+      local dir
+      if arg == option then
+        i = i + 1
+        dir = args[i]
+      elseif vim.startswith(arg, option) and arg ~= option then
+        dir = arg:sub(#option + 1)
+      end
+      if dir and dir ~= "" then
+        add_dir(flags[field], vim.startswith(dir, "/") and dir or directory .. "/" .. dir, canonical)
+        break
+      end
+    end
+    i = i + 1
+  end
+end
+
+---Read a compile_commands.json file
+---@param path string Absolute file path
+---@return CompileDatabase database
+local function read_database(path)
+  local database = { files = {}, all = { quote = {}, include = {}, system = {} }, sources = {} }
+  local canonical = {}
+  local ok, entries = pcall(vim.fn.json_decode, utils.read_file_content(path) or "")
+  if not ok or type(entries) ~= "table" then
+    return database
+  end
+
+  for _, entry in ipairs(entries) do
+    local directory = entry.directory or vim.fn.fnamemodify(path, ":h")
+    local args = entry.arguments or (entry.command and split_command(entry.command)) or {}
+    if entry.file and #args > 0 then
+      local file = vim.startswith(entry.file, "/") and entry.file or directory .. "/" .. entry.file
+      file = utils.canonical_path(file)
+
+      local flags = { quote = {}, include = {}, system = {} }
+      parse_flags(args, directory, flags, canonical)
+      database.files[file] = flags
+      parse_flags(args, directory, database.all, canonical)
+
+      local stem = vim.fn.fnamemodify(file, ":t:r")
+      database.sources[stem] = database.sources[stem] or {}
+      table.insert(database.sources[stem], file)
+    end
+  end
+  return database
+end
+
+---Find and read the compilation database of a project: at its root or in a build directory directly below it
+---@param root string Canonical project root
+---@return CompileDatabase|nil database
+local function find_database(root)
+  local path = root .. "/compile_commands.json"
+  if vim.fn.filereadable(path) ~= 1 then
+    path = vim.fn.glob(root .. "/*/compile_commands.json", false, true)[1]
+  end
+  if not path then
+    return nil
+  end
+
+  local mtime = vim.fn.getftime(path)
+  local cached = databases[path]
+  if not cached or cached.mtime ~= mtime then
+    cached = { mtime = mtime, database = read_database(path) }
+    databases[path] = cached
+  end
+  return cached.database
+end
+
+---Get the default system include directories of a compiler
+---@param compiler string Compiler executable
+---@param lang "c"|"c++" Language
+---@return string[] dirs
+local function get_system_dirs(compiler, lang)
+  local key = compiler .. ":" .. lang
+  if system_dirs[key] then
+    return system_dirs[key]
+  end
+
+  local dirs = {}
+  if vim.fn.executable(compiler) == 1 then
+    -- The search list is printed to stderr between these two lines
+    local output = vim.fn.systemlist({ compiler, "-E", "-x", lang, "-", "-v" }, "")
+    local in_list = false
+    for _, line in ipairs(output) do
+      if line:match("^#include <%.%.%.> search starts here") then
+        in_list = true
+      elseif line:match("^End of search list") then
+        break
+      elseif in_list then
+        local dir = vim.trim((line:gsub("%(framework directory%)", "")))
+        if vim.fn.isdirectory(dir) == 1 then
+          add_dir(dirs, dir, {})
+        end
+      end
+    end
+  end
+
+  system_dirs[key] = dirs
+  return dirs
+end
+
+---Get the include search directories of a file
+---@param from_file string Canonical path of the including file
+---@return CompileFlags flags
+---@return string[] defaults Default system directories of the compiler
+local function get_flags(from_file)
+  local root = utils.canonical_path(project.find_root(from_file))
+  local database = find_database(root)
+  local flags = database and (database.files[from_file] or database.all) or { quote = {}, include = {}, system = {} }
+
+  local is_c = vim.fn.fnamemodify(from_file, ":e") == "c"
+  local compiler = flags.compiler or (is_c and "cc" or "c++")
+  return flags, get_system_dirs(compiler, is_c and "c" or "c++")
+end
+
+---Find a header below the first directory containing it
+---@param dirs string[] Directories in search order
+---@param header string Header path as written
+---@return string|nil path
+local function find_header(dirs, header)
+  for _, dir in ipairs(dirs) do
+    local path = dir .. "/" .. header
+    if vim.fn.filereadable(path) == 1 then
+      return utils.canonical_path(path)
+    end
+  end
+  return nil
+end
+
+---Resolve an include to a header file
+---Quoted includes search the including file's directory and `-iquote` dirs first; both forms then search
+---`-I`, `-isystem` and the compiler's default directories
+---@param import_path string Header as written, `<vector>` for system includes
+---@param from_file string Absolute path of the including file
+---@return string|nil path
+function M.resolve_import(import_path, from_file)
+  from_file = utils.canonical_path(from_file)
+  local header = import_path:match("^<(.*)>$")
+  local flags, defaults = get_flags(from_file)
+
+  local dirs = {}
+  if not header then
+    header = import_path
+    table.insert(dirs, vim.fn.fnamemodify(from_file, ":h"))
+    vim.list_extend(dirs, flags.quote)
+  end
+  vim.list_extend(dirs, flags.include)
+  vim.list_extend(dirs, flags.system)
+  vim.list_extend(dirs, defaults)
+  return find_header(dirs, header)
+end
+
+---Check whether an include is a system header
+---@param import_path string Header as written
+---@param resolved string|nil Resolved path
+---@param from_file string Absolute path of the including file
+---@return boolean
+function M.is_stdlib(import_path, resolved, from_file)
+  if not resolved then
+    return import_path:match("^<.*>$") ~= nil
+  end
+
+  local flags, defaults = get_flags(utils.canonical_path(from_file))
+  for _, dir in ipairs(vim.list_extend(vim.list_extend({}, defaults), flags.system)) do
+    if vim.startswith(resolved, dir .. "/") then
+      return true
+    end
+  end
+  return false
+end
+
+---Find the source files implementing a project header: next to it, in a sibling `src/` of an `include/` dir,
+---or compiled files of the same name in compile_commands.json
+---@param resolved string Resolved header path
+---@param from_file string Absolute path of the including file
+---@return string[] sources
+function M.get_related(resolved, from_file)
+  from_file = utils.canonical_path(from_file)
+  local root = utils.canonical_path(project.find_root(from_file))
+  if not vim.startswith(resolved, root .. "/") or not resolved:match("%.h[hpx]*$") then
+    return {}
+  end
+
+  local stem = vim.fn.fnamemodify(resolved, ":r")
+  local bases = { stem, (stem:gsub("/include/", "/src/")) }
+  for _, base in ipairs(bases) do
+    for _, extension in ipairs(SOURCE_EXTENSIONS) do
+      local source = base .. "." .. extension
+      if source ~= from_file and vim.fn.filereadable(source) == 1 then
+        return { utils.canonical_path(source) }
+      end
+    end
+  end
+
+  local database = find_database(root)
+  local sources = database and database.sources[vim.fn.fnamemodify(resolved, ":t:r")] or {}
+  return vim.tbl_filter(function(source)
+    return source ~= from_file
+  end, sources)
+end
+
+return M
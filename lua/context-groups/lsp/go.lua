@@ -0,0 +1,263 @@
+-- lua/context-groups/lsp/go.lua
+-- Go import resolution to package directories through go.mod, GOROOT and the module cache
+
+local config = require("context-groups.config")
+local utils = require("context-groups.utils")
+
+---@type ImportHandler
+local M = {}
+
+---@class GoModule
+---@field dir string Canonical directory of go.mod
+---@field path string|nil Module path
+---@field requires table<string, string> Required versions by module path
+---@field replaces table<string, string> Replacements by module path: a local directory or `module version`
+
+-- Parsed go.mod files by path, with the mtime they were read at
+---@type table<string, {mtime: integer|nil, module: GoModule}>
+local go_mods = {}
+
+---Get an existing directory from the Go language config
+---@param field "stdlib_path"|"external_path" Config field
+---@return string|nil dir Nil when unset or not a directory (e.g. `go env` failed)
+local function config_dir(field)
+  local dir = config.get_language_config("go")[field]
+  return dir and vim.fn.isdirectory(dir) == 1 and utils.canonical_path(dir) or nil
+end
+
+---Parse a go.mod file
+---@param path string Absolute go.mod path
+---@return GoModule module
+local function parse_go_mod(path)
+  local module = { dir = utils.canonical_path(vim.fn.fnamemodify(path, ":h")), requires = {}, replaces = {} }
+  local block
+  local versioned = {}
+
+  for line in (utils.read_file_content(path) or ""):gmatch("[^\n]+") do
+    line = vim.trim((line:gsub("//.*$", "")))
+    local directive, rest
+    if line == ")" then
+      block = nil
+    elseif block then
+      directive, rest = block, line
+    elseif line:match("^%w+%s*%($") then
+      block = line:match("^(%w+)")
+    else
+      directive, rest = line:match("^(%w+)%s+(.+)$")
+    end
+
+    if directive == "module" then
+      module.path = (rest:gsub('^"(.*)"$', "%1"))
+    elseif directive == "require" then
+      local name, version = rest:match("^(%S+)%s+(%S+)")
+      if name then
+        module.requires[name] = version
+      end
+    elseif directive == "replace" then
+      -- `old => new` replaces every version, `old v1.2.3 => new` only the version it names
+      local name, version, replacement = rest:match("^(%S+)%s*(%S*)%s*=>%s*(.-)$")
+      if name and version == "" then
+        module.replaces[name] = replacement
+      elseif name then
+        table.insert(versioned, { name = name, version = version, replacement = replacement })
+      end
+    end
+  end
+
+  -- Version-specific replacements win over whole-module ones, and requirements may come after them
+  for _, replace in ipairs(versioned) do
+    if module.requires[replace.name] == replace.version then
+      module.replaces[replace.name] = replace.replacement
+    end
+  end
+
+  return module
+end
+
+---Find and parse the go.mod governing a file, reading it again only when it changed
+---@param from_file string Absolute file path
+---@return GoModule|nil module
+local function find_module(from_file)
+  local go_mod = vim.fn.findfile("go.mod", vim.fn.fnamemodify(from_file, ":h") .. ";")
+  if go_mod == "" then
+    return nil
+  end
+
+  go_mod = vim.fn.fnamemodify(go_mod, ":p")
+  local mtime = vim.fn.getftime(go_mod)
+  local cached = go_mods[go_mod]
+  if not cached or cached.mtime ~= mtime then
+    cached = { mtime = mtime, module = parse_go_mod(go_mod) }
+    go_mods[go_mod] = cached
+  end
+  return cached.module
+end
+
+---Escape a module path or version as the module cache does: upper-case letters become `!` and the lower-case letter
+---@param path string Module path or version
+---@return string escaped
+local function escape_module_path(path)
+  return (path:gsub("%u", function(letter)
+    return "!" .. letter:lower()
+  end))
+end
+
+---Get the directory of a module version in the module cache
+---@param name string Module path
+---@param version string Module version
+---@return string|nil dir
+local function module_cache_dir(name, version)
+  local modcache = vim.env.GOMODCACHE
+  if not modcache or modcache == "" then
+    local gopath = config_dir("external_path")
+    if not gopath then
+      return nil
+    end
+    modcache = gopath .. "/pkg/mod"
+  end
+  return modcache .. "/" .. escape_module_path(name) .. "@" .. escape_module_path(version)
+end
+
+---Find the longest module path in a table that an import belongs to
+---@param import_path string Import path
+---@param modules table<string, string> Values by module path
+---@return string|nil name, string|nil value, string rest Package path inside the module, "" for its root
+local function match_module(import_path, modules)
+  local best
+  for name in pairs(modules) do
+    if (import_path == name or vim.startswith(import_path, name .. "/")) and (not best or #name > #best) then
+      best = name
+    end
+  end
+  return best, best and modules[best], best and import_path:sub(#best + 1) or ""
+end
+
+---Check whether an import path names a standard library package: its first element has no dot
+---@param import_path string Import path
+---@return boolean
+local function is_stdlib_path(import_path)
+  return not import_path:match("^[^/]*%.")
+end
+
+---Return a directory when it exists
+---@param dir string|nil Directory
+---@return string|nil dir Canonical directory
+local function existing_dir(dir)
+  return dir and vim.fn.isdirectory(dir) == 1 and utils.canonical_path(dir) or nil
+end
+
+---Resolve a Go import path to its package directory
+---@param import_path string Import path
+---@param from_file string Absolute path of the importing file
+---@return string|nil dir
+function M.resolve_import(import_path, from_file)
+  local module = find_module(from_file)
+
+  if module and module.path then
+    if import_path == module.path or vim.startswith(import_path, module.path .. "/") then
+      return existing_dir(module.dir .. import_path:sub(#module.path + 1))
+    end
+
+    local vendored = existing_dir(module.dir .. "/vendor/" .. import_path)
+    if vendored then
+      return vendored
+    end
+
+    local name, replacement, rest = match_module(import_path, module.replaces)
+    if name and replacement then
+      if replacement:match("^%.%.?/") or vim.startswith(replacement, "/") then
+        local dir = vim.startswith(replacement, "/") and replacement or module.dir .. "/" .. replacement
+        return existing_dir(dir .. rest)
+      end
+      local new_name, version = replacement:match("^(%S+)%s+(%S+)$")
+      local dir = new_name and module_cache_dir(new_name, version)
+      return dir and existing_dir(dir .. rest)
+    end
+
+    local required, version
+    required, version, rest = match_module(import_path, module.requires)
+    if required and version then
+      local dir = module_cache_dir(required, version)
+      return dir and existing_dir(dir .. rest)
+    end
+  end
+
+  local goroot = config_dir("stdlib_path")
+  if goroot and is_stdlib_path(import_path) then
+    return existing_dir(goroot .. "/src/" .. import_path)
+  end
+  return nil
+end
+
+-- GOOS and GOARCH values that restrict a file to one platform as a `_GOOS`, `_GOARCH` or `_GOOS_GOARCH` suffix
+local KNOWN_OS, KNOWN_ARCH = {}, {}
+for _, name in ipairs({
+  "aix", "android", "darwin", "dragonfly", "freebsd", "hurd", "illumos", "ios", "js", "linux", "nacl", "netbsd",
+  "openbsd", "plan9", "solaris", "wasip1", "windows", "zos",
+}) do
+  KNOWN_OS[name] = true
+end
+for _, name in ipairs({
+  "386", "amd64", "arm", "arm64", "loong64", "mips", "mips64", "mips64le", "mipsle", "ppc64", "ppc64le", "riscv64",
+  "s390x", "wasm",
+}) do
+  KNOWN_ARCH[name] = true
+end
+
+---Get the GOOS and GOARCH of the running system
+---@return string goos, string goarch
+local function host_platform()
+  local uname = (vim.uv or vim.loop).os_uname()
+  local goos = vim.fn.has("win32") == 1 and "windows" or uname.sysname:lower()
+  local machine = uname.machine:lower()
+  local goarch = ({ x86_64 = "amd64", aarch64 = "arm64", i386 = "386", i686 = "386" })[machine] or machine
+  return goos, goarch
+end
+
+---Check whether a file name restricts a Go file to another platform, like `poll_windows.go` on Linux
+---@param name string File name without `.go`
+---@return boolean
+local function is_other_platform(name)
+  local goos, goarch = host_platform()
+  local parts = vim.split(name, "_", { plain = true })
+  local last, before = parts[#parts], parts[#parts - 1]
+  if #parts >= 3 and KNOWN_OS[before] and KNOWN_ARCH[last] then
+    return before ~= goos or last ~= goarch
+  end
+  if #parts >= 2 and KNOWN_OS[last] then
+    return last ~= goos
+  end
+  if #parts >= 2 and KNOWN_ARCH[last] then
+    return last ~= goarch
+  end
+  return false
+end
+
+---Get the files of a package that are built with it: tests and files for other platforms are left out
+---@param dir string Canonical package directory
+---@return string[] files Canonical paths
+function M.package_files(dir)
+  local files = {}
+  for _, file in ipairs(vim.fn.glob(dir .. "/*.go", false, true)) do
+    local name = vim.fn.fnamemodify(file, ":t:r")
+    if vim.fn.isdirectory(file) == 0 and not vim.endswith(name, "_test") and not is_other_platform(name) then
+      table.insert(files, utils.canonical_path(file))
+    end
+  end
+  table.sort(files)
+  return files
+end
+
+---Check whether an import is a standard library package
+---@param import_path string Import path
+---@param resolved string|nil Resolved package directory
+---@return boolean
+function M.is_stdlib(import_path, resolved)
+  if resolved then
+    local goroot = config_dir("stdlib_path")
+    return goroot ~= nil and vim.startswith(resolved, goroot .. "/")
+  end
+  return is_stdlib_path(import_path)
+end
+
+return M
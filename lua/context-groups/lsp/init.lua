@@ -0,0 +1,325 @@
+-- lua/context-groups/lsp/init.lua
+-- Import extraction and resolution per language, extensible with handlers
+
+local config = require("context-groups.config")
+local project = require("context-groups.project")
+local utils = require("context-groups.utils")
+
+local M = {}
+
+---@class ImportHandler
+---@field get_imports? fun(bufnr: integer): (string|{name: string, line: integer})[] Imports of a buffer as written
+---@field expand_import? fun(import_path: string): string[] Imports named by one import statement, such as the leaves of a use tree
+---@field resolve_import? fun(import_path: string, from_file: string): string|nil File or package directory of an import
+---@field is_stdlib? fun(import_path: string, resolved: string|nil, from_file: string): boolean Whether an import is from the standard library
+---@field is_external? fun(import_path: string, resolved: string|nil, from_file: string): boolean Whether an import is a dependency
+---@field get_related? fun(resolved: string, from_file: string): string[] Files that belong with a resolved import
+---@field package_files? fun(dir: string, from_file: string): string[] Source files an import of a package directory stands for
+
+---@class ImportInfo
+---@field name string Import as written in the source, or one of the imports it expands to
+---@field line integer 1-based line of the import
+---@field path string|nil Absolute file or package directory the import resolves to
+---@field kind "project"|"stdlib"|"external" Where the import comes from
+---@field related string[]|nil Files added along with the import, such as the source file of a header
+---@field files string[]|nil Source files of a project package directory the import resolves to
+
+-- Handlers by filetype or treesitter language; missing functions use the generic implementation
+---@type table<string, ImportHandler>
+local handlers = {}
+
+-- Treesitter queries capturing the module of every import as @import
+local IMPORT_QUERIES = {
+  go = [[(import_spec path: (_) @import)]],
+  python = [[
+    (import_statement name: (dotted_name) @import)
+    (import_statement name: (aliased_import name: (dotted_name) @import))
+    (import_from_statement module_name: (_) @import)
+  ]],
+  javascript = [[
+    (import_statement source: (string) @import)
+    (export_statement source: (string) @import)
+    (call_expression
+      function: (identifier) @_fn
+      arguments: (arguments . (string) @import)
+      (#eq? @_fn "require"))
+  ]],
+  lua = [[
+    (function_call
+      name: (identifier) @_fn
+      arguments: (arguments . (string) @import)
+      (#eq? @_fn "require"))
+  ]],
+  rust = [[
+    (use_declaration argument: (_) @import)
+    (mod_item name: (identifier) @import !body)
+  ]],
+  c = [[(preproc_include path: (_) @import)]],
+}
+IMPORT_QUERIES.typescript = IMPORT_QUERIES.javascript
+IMPORT_QUERIES.tsx = IMPORT_QUERIES.javascript
+IMPORT_QUERIES.cpp = IMPORT_QUERIES.c
+
+-- Built-in handlers by treesitter language, used unless a handler is registered or the language's `resolve` is
+-- false
+local BUILTIN_HANDLERS = {
+  go = "context-groups.lsp.go",
+  python = "context-groups.lsp.python",
+  rust = "context-groups.lsp.rust",
+  javascript = "context-groups.lsp.typescript",
+  typescript = "context-groups.lsp.typescript",
+  tsx = "context-groups.lsp.typescript",
+  lua = "context-groups.lsp.lua",
+  c = "context-groups.lsp.c",
+  cpp = "context-groups.lsp.c",
+}
+
+---Register a handler for a language
+---@param lang string Filetype or treesitter language
+---@param handler ImportHandler Handler; functions it leaves out use the generic implementation
+function M.register_handler(lang, handler)
+  handlers[lang] = handler
+end
+
+---Get the handler and treesitter language of a filetype
+---@param filetype string|nil Filetype
+---@return ImportHandler handler, string|nil lang
+local function get_handler(filetype)
+  if not filetype or filetype == "" then
+    return {}, nil
+  end
+  local get_lang_fn = vim.treesitter.language.get_lang or function(ft)
+    return ft
+  end
+  local lang = get_lang_fn(filetype) or filetype
+
+  local handler = handlers[filetype] or handlers[lang]
+  if not handler and BUILTIN_HANDLERS[lang] and config.get_language_config(lang).resolve ~= false then
+    handler = require(BUILTIN_HANDLERS[lang])
+  end
+  return handler or {}, lang
+end
+
+---Parse the imports of a buffer or file content with treesitter
+---@param source integer|string Buffer number or file content
+---@param lang string|nil Treesitter language
+---@return {name: string, line: integer}[] imports
+local function treesitter_imports(source, lang)
+  local query_source = lang and IMPORT_QUERIES[lang]
+  if not query_source then
+    return {}
+  end
+
+  local get_parser = type(source) == "string" and vim.treesitter.get_string_parser or vim.treesitter.get_parser
+  local ok, parser = pcall(get_parser, source, lang)
+  if not ok or not parser then
+    return {}
+  end
+  local parse_query = vim.treesitter.query.parse or vim.treesitter.parse_query
+  local query_ok, query = pcall(parse_query, lang, query_source)
+  if not query_ok then
+    return {}
+  end
+
+  local imports = {}
+  local tree = parser:parse()[1]
+  for id, node in query:iter_captures(tree:root(), source, 0, -1) do
+    if query.captures[id] == "import" then
+      -- Strip the quotes of string literals; system includes keep their brackets to tell them from quoted ones
+      local text = vim.treesitter.get_node_text(node, source)
+      local name = text:gsub("^[\"'`]", ""):gsub("[\"'`]$", "")
+      table.insert(imports, { name = name, line = node:start() + 1 })
+    end
+  end
+  return imports
+end
+
+---Find the first existing file among import path candidates
+---A candidate may be the file itself, the file with the importing file's extension, or a package directory
+---with an index file; a package directory without one is returned as is (Go packages)
+---@param bases string[] Absolute candidate paths without extension
+---@param ext string Extension of the importing file
+---@return string|nil path
+function M.find_candidate(bases, ext)
+  local suffixes = { "", "." .. ext, "/index." .. ext, "/__init__." .. ext, "/init." .. ext, "/mod." .. ext }
+  for _, base in ipairs(bases) do
+    for _, suffix in ipairs(suffixes) do
+      local path = base .. suffix
+      if vim.fn.filereadable(path) == 1 and vim.fn.isdirectory(path) == 0 then
+        return utils.canonical_path(path)
+      end
+    end
+    if vim.fn.isdirectory(base) == 1 then
+      return utils.canonical_path(base)
+    end
+  end
+  return nil
+end
+
+---Get the source files directly inside a package directory with the importing file's extension
+---@param dir string Canonical package directory
+---@param from_file string Absolute path of the importing file
+---@return string[] files Canonical paths
+function M.default_package_files(dir, from_file)
+  local files = {}
+  for _, file in ipairs(vim.fn.glob(dir .. "/*." .. vim.fn.fnamemodify(from_file, ":e"), false, true)) do
+    if vim.fn.isdirectory(file) == 0 then
+      table.insert(files, utils.canonical_path(file))
+    end
+  end
+  table.sort(files)
+  return files
+end
+
+---Resolve an import to a project file with generic path rules
+---Relative imports are resolved from the importing file; module paths (`a.b.c`, `a::b`, `a/b`) from the
+---file's directory and the project root, dropping leading segments until a match is found
+---@param import_path string Import as written
+---@param from_file string Absolute path of the importing file
+---@return string|nil path
+function M.default_resolve_import(import_path, from_file)
+  import_path = import_path:gsub("^<(.*)>$", "%1")
+  local dir = vim.fn.fnamemodify(from_file, ":h")
+  local ext = vim.fn.fnamemodify(from_file, ":e")
+
+  if vim.startswith(import_path, "/") then
+    return M.find_candidate({ import_path }, ext)
+  end
+  if import_path:match("^%.%.?/") then
+    return M.find_candidate({ dir .. "/" .. import_path }, ext)
+  end
+
+  local root = utils.canonical_path(project.find_root(from_file))
+  local bases = { dir .. "/" .. import_path, root .. "/" .. import_path }
+  local segments = vim.split(import_path:gsub("::", "/"):gsub("%.", "/"), "/", { trimempty = true })
+  for i = 1, #segments do
+    table.insert(bases, root .. "/" .. table.concat(segments, "/", i))
+  end
+  return M.find_candidate(bases, ext)
+end
+
+---Classify an import
+---@param handler ImportHandler Language handler
+---@param lang string|nil Treesitter language
+---@param name string Import as written
+---@param path string|nil Resolved path
+---@param from_file string Canonical path of the importing file
+---@param root string Canonical project root
+---@return "project"|"stdlib"|"external" kind
+local function classify(handler, lang, name, path, from_file, root)
+  if handler.is_stdlib and handler.is_stdlib(name, path, from_file) then
+    return "stdlib"
+  end
+  if handler.is_external and handler.is_external(name, path, from_file) then
+    return "external"
+  end
+  if path and vim.startswith(path, root .. "/") then
+    return "project"
+  end
+
+  local stdlib_path = lang and config.get_language_config(lang).stdlib_path
+  if path and stdlib_path and vim.startswith(path, stdlib_path) then
+    return "stdlib"
+  end
+  return "external"
+end
+
+---Check whether an import matches one of the ignore patterns
+---@param import ImportInfo Import
+---@param patterns string[] Lua patterns
+---@return boolean ignored
+local function is_ignored(import, patterns)
+  for _, pattern in ipairs(patterns) do
+    if import.name:match(pattern) or (import.path and import.path:match(pattern)) then
+      return true
+    end
+  end
+  return false
+end
+
+---Resolve, classify and filter the imports parsed from a file
+---@param raw (string|{name: string, line: integer})[] Imports as written
+---@param from_file string Canonical path of the importing file
+---@param handler ImportHandler Language handler
+---@param lang string|nil Treesitter language
+---@param opts? {all: boolean} Options {all: Ignore import_prefs}
+---@return ImportInfo[] imports
+local function collect_imports(raw, from_file, handler, lang, opts)
+  local root = utils.canonical_path(project.find_root(from_file))
+  local prefs = config.get_import_prefs()
+  local resolve = handler.resolve_import or M.default_resolve_import
+
+  local imports = {}
+  local seen = {}
+  for _, item in ipairs(raw) do
+    local written = type(item) == "table" and item.name or item
+    for _, name in ipairs(handler.expand_import and handler.expand_import(written) or { written }) do
+      if not seen[name] then
+        seen[name] = true
+        local path = resolve(name, from_file)
+        local import = {
+          name = name,
+          line = type(item) == "table" and item.line or 0,
+          path = path ~= from_file and path or nil,
+          kind = classify(handler, lang, name, path, from_file, root),
+        }
+        if import.path and handler.get_related then
+          import.related = handler.get_related(import.path, from_file)
+        end
+        if import.kind == "project" and import.path and vim.fn.isdirectory(import.path) == 1 then
+          import.files = (handler.package_files or M.default_package_files)(import.path, from_file)
+        end
+
+        local shown = (opts and opts.all)
+          or (import.kind == "project" or (import.kind == "stdlib" and prefs.show_stdlib))
+          or (import.kind == "external" and prefs.show_external)
+        if shown and not is_ignored(import, prefs.ignore_patterns or {}) then
+          table.insert(imports, import)
+        end
+      end
+    end
+  end
+
+  return imports
+end
+
+---Get the imports of a buffer, resolved to files and classified
+---Standard library and external imports are left out unless `import_prefs` shows them
+---@param bufnr? integer Buffer number (default current buffer)
+---@param opts? {all: boolean} Options {all: Ignore import_prefs}
+---@return ImportInfo[] imports In source order, without duplicates
+function M.get_imports(bufnr, opts)
+  bufnr = (bufnr == nil or bufnr == 0) and vim.api.nvim_get_current_buf() or bufnr
+  local from_file = vim.api.nvim_buf_get_name(bufnr)
+  if from_file == "" then
+    return {}
+  end
+
+  local handler, lang = get_handler(vim.bo[bufnr].filetype)
+  local raw = handler.get_imports and handler.get_imports(bufnr) or treesitter_imports(bufnr, lang)
+  return collect_imports(raw, utils.canonical_path(from_file), handler, lang, opts)
+end
+
+---Get the imports of a file, from its buffer when it is loaded and from disk otherwise
+---Handlers' `get_imports` needs a buffer, so files read from disk are always parsed with treesitter
+---@param path string File path
+---@param opts? {all: boolean} Options {all: Ignore import_prefs}
+---@return ImportInfo[] imports In source order, without duplicates
+function M.get_file_imports(path, opts)
+  path = utils.canonical_path(path)
+  for _, bufnr in ipairs(vim.api.nvim_list_bufs()) do
+    local name = vim.api.nvim_buf_get_name(bufnr)
+    if name ~= "" and vim.api.nvim_buf_is_loaded(bufnr) and utils.canonical_path(name) == path then
+      return M.get_imports(bufnr, opts)
+    end
+  end
+
+  local content = utils.read_file_content(path)
+  if not content then
+    return {}
+  end
+  local handler, lang = get_handler(vim.filetype.match({ filename = path }))
+  return collect_imports(treesitter_imports(content, lang), path, handler, lang, opts)
+end
+
+return M
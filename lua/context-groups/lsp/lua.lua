@@ -0,0 +1,116 @@
+-- lua/context-groups/lsp/lua.lua
+-- Lua require resolution for Neovim plugins: the project's lua/ dir, the runtimepath and installed plugins
+
+local config = require("context-groups.config")
+local project = require("context-groups.project")
+local utils = require("context-groups.utils")
+
+---@type ImportHandler
+local M = {}
+
+-- Modules of Lua, LuaJIT and Neovim that have no file of their own
+local BUILTIN_MODULES = {
+  bit = true,
+  coroutine = true,
+  debug = true,
+  ffi = true,
+  io = true,
+  jit = true,
+  math = true,
+  os = true,
+  package = true,
+  string = true,
+  table = true,
+  utf8 = true,
+  vim = true,
+}
+
+-- Plugin directories installed by lazy.nvim, packer and native packages, listed on first use
+---@type string[]|nil
+local plugin_dirs = nil
+
+---List the directories of installed plugins, including ones lazy.nvim has not loaded yet
+---@return string[] dirs
+local function get_plugin_dirs()
+  if plugin_dirs then
+    return plugin_dirs
+  end
+
+  local data = vim.fn.stdpath("data")
+  local ok, lazy_config = pcall(require, "lazy.core.config")
+  local lazy_root = ok and lazy_config.options and lazy_config.options.root or data .. "/lazy"
+
+  plugin_dirs = {}
+  local patterns = { lazy_root .. "/*", data .. "/site/pack/*/start/*", data .. "/site/pack/*/opt/*" }
+  for _, pattern in ipairs(patterns) do
+    vim.list_extend(plugin_dirs, vim.fn.glob(pattern, false, true))
+  end
+  return plugin_dirs
+end
+
+---Find a module file below Lua source directories
+---@param dirs string[] Directories holding modules, e.g. `<plugin>/lua`
+---@param rel_path string Module name with dots replaced by slashes
+---@return string|nil path
+local function find_module(dirs, rel_path)
+  for _, dir in ipairs(dirs) do
+    for _, candidate in ipairs({ dir .. "/" .. rel_path .. ".lua", dir .. "/" .. rel_path .. "/init.lua" }) do
+      if vim.fn.filereadable(candidate) == 1 then
+        return utils.canonical_path(candidate)
+      end
+    end
+  end
+  return nil
+end
+
+---Resolve a required module to its file
+---@param import_path string Module name as passed to require
+---@param from_file string Absolute path of the requiring file
+---@return string|nil path
+function M.resolve_import(import_path, from_file)
+  local rel_path = import_path:gsub("%.", "/")
+  local root = utils.canonical_path(project.find_root(from_file))
+
+  -- The project's own modules, in plugin layout or loose next to the root
+  local path = find_module({ root .. "/lua", root }, rel_path)
+  if path then
+    return path
+  end
+
+  -- Neovim's runtime modules (vim.lsp, vim.treesitter) count as standard library and resolve regardless
+  if BUILTIN_MODULES[import_path:match("^[^.]+")] then
+    return find_module({ vim.env.VIMRUNTIME .. "/lua" }, rel_path)
+  end
+  if not config.get_import_prefs().show_external then
+    return nil
+  end
+
+  local candidates = vim.api.nvim_get_runtime_file("lua/" .. rel_path .. ".lua", false)
+  if #candidates == 0 then
+    candidates = vim.api.nvim_get_runtime_file("lua/" .. rel_path .. "/init.lua", false)
+  end
+  if candidates[1] then
+    return utils.canonical_path(candidates[1])
+  end
+
+  -- Plugins lazy.nvim has not loaded are missing from the runtimepath
+  return find_module(
+    vim.tbl_map(function(dir)
+      return dir .. "/lua"
+    end, get_plugin_dirs()),
+    rel_path
+  )
+end
+
+---Check whether a module belongs to Lua or Neovim
+---@param import_path string Module name
+---@param resolved string|nil Resolved path
+---@return boolean
+function M.is_stdlib(import_path, resolved)
+  if resolved then
+    return vim.startswith(resolved, utils.canonical_path(vim.env.VIMRUNTIME) .. "/")
+  end
+  return BUILTIN_MODULES[import_path:match("^[^.]+")] == true
+end
+
+return M
@@ -0,0 +1,223 @@
+-- lua/context-groups/lsp/python.lua
+-- Python import resolution to project modules, the virtualenv's site-packages and the standard library
+
+local config = require("context-groups.config")
+local lsp = require("context-groups.lsp")
+local project = require("context-groups.project")
+local utils = require("context-groups.utils")
+
+---@type ImportHandler
+local M = {}
+
+---@class PythonEnv
+---@field venv string|nil Canonical virtualenv directory
+---@field site_packages string[] Canonical site-packages directories of the virtualenv
+---@field stdlib string|nil Canonical standard library directory
+---@field stdlib_modules table<string, boolean> Top-level standard library module names
+
+-- Environments by project root, detected on first use
+---@type table<string, PythonEnv>
+local envs = {}
+
+-- Prints the standard library directory, then its module names (Python 3.10+)
+local STDLIB_SCRIPT = [[
+import sys, sysconfig
+print(sysconfig.get_paths()["stdlib"])
+print(" ".join(sorted(getattr(sys, "stdlib_module_names", ()))))
+]]
+
+---Check whether a directory is a virtualenv
+---@param dir string Directory
+---@return boolean
+local function is_venv(dir)
+  return vim.fn.filereadable(dir .. "/pyvenv.cfg") == 1
+end
+
+---Find the virtualenv of a project: the active one, one inside the project (`.venv` of uv and in-project poetry,
+---`venv`, `env`), or the one poetry created for it
+---@param root string Canonical project root
+---@return string|nil venv Canonical virtualenv directory
+local function find_venv(root)
+  local active = vim.env.VIRTUAL_ENV
+  if active and active ~= "" and is_venv(active) then
+    return utils.canonical_path(active)
+  end
+
+  local candidates = { ".venv", "venv", "env" }
+  if vim.env.UV_PROJECT_ENVIRONMENT and vim.env.UV_PROJECT_ENVIRONMENT ~= "" then
+    table.insert(candidates, 1, vim.env.UV_PROJECT_ENVIRONMENT)
+  end
+  for _, dir in ipairs(candidates) do
+    local path = vim.startswith(dir, "/") and dir or root .. "/" .. dir
+    if is_venv(path) then
+      return utils.canonical_path(path)
+    end
+  end
+
+  if vim.fn.filereadable(root .. "/poetry.lock") == 1 and vim.fn.executable("poetry") == 1 then
+    local path = vim.fn.systemlist({ "poetry", "-C", root, "env", "info", "--path" })[1]
+    if vim.v.shell_error == 0 and path and is_venv(path) then
+      return utils.canonical_path(path)
+    end
+  end
+  return nil
+end
+
+---Detect the Python environment of a project
+---@param root string Canonical project root
+---@return PythonEnv env
+local function get_env(root)
+  if envs[root] then
+    return envs[root]
+  end
+
+  local env = { site_packages = {}, stdlib_modules = {} }
+  if config.get_language_config("python").venv_detection ~= false then
+    env.venv = find_venv(root)
+  end
+
+  local python = "python3"
+  if env.venv then
+    python = env.venv .. "/bin/python"
+    local patterns = { env.venv .. "/lib/python*/site-packages", env.venv .. "/Lib/site-packages" }
+    for _, pattern in ipairs(patterns) do
+      for _, dir in ipairs(vim.fn.glob(pattern, false, true)) do
+        table.insert(env.site_packages, utils.canonical_path(dir))
+      end
+    end
+  end
+
+  if vim.fn.executable(python) == 1 then
+    local output = vim.fn.systemlist({ python, "-c", STDLIB_SCRIPT })
+    if vim.v.shell_error == 0 and output[1] and vim.fn.isdirectory(output[1]) == 1 then
+      env.stdlib = utils.canonical_path(output[1])
+      for _, name in ipairs(vim.split(output[2] or "", " ", { trimempty = true })) do
+        env.stdlib_modules[name] = true
+      end
+    end
+  end
+
+  envs[root] = env
+  return env
+end
+
+---Find a module below one of several directories
+---`a.b.c` is tried as a module or package, then as `a.b` and `a` since imported names may be attributes
+---@param dirs string[] Directories on the module search path
+---@param segments string[] Dotted module path split at the dots
+---@return string|nil path
+local function find_module(dirs, segments)
+  for count = #segments, 1, -1 do
+    local rel_path = table.concat(segments, "/", 1, count)
+    local bases = vim.tbl_map(function(dir)
+      return dir .. "/" .. rel_path
+    end, dirs)
+    -- Type stubs stand in for compiled modules
+    local path = lsp.find_candidate(bases, "py") or lsp.find_candidate(bases, "pyi")
+    if path then
+      return path
+    end
+  end
+  return nil
+end
+
+---Resolve a relative import (`.models`, `..utils.io`, `.`) against the importing file's package
+---@param import_path string Import as written
+---@param from_file string Absolute path of the importing file
+---@return string|nil path
+local function resolve_relative(import_path, from_file)
+  local dots, module = import_path:match("^(%.+)(.*)$")
+  local dir = vim.fn.fnamemodify(from_file, ":h")
+  for _ = 2, #dots do
+    dir = vim.fn.fnamemodify(dir, ":h")
+  end
+
+  if module == "" then
+    return lsp.find_candidate({ dir .. "/__init__" }, "py")
+  end
+  return find_module({ dir }, vim.split(module, ".", { plain = true }))
+end
+
+---Resolve a Python import to a module file or package
+---@param import_path string Import as written, e.g. `a.b`, `.models` or `..`
+---@param from_file string Absolute path of the importing file
+---@return string|nil path
+function M.resolve_import(import_path, from_file)
+  if vim.startswith(import_path, ".") then
+    return resolve_relative(import_path, from_file)
+  end
+
+  local root = utils.canonical_path(project.find_root(from_file))
+  local env = get_env(root)
+  local segments = vim.split(import_path, ".", { plain = true })
+
+  -- Project modules: next to the importing file, at the root and in a src layout
+  local dirs = { vim.fn.fnamemodify(from_file, ":h"), root, root .. "/src" }
+  local path = find_module(dirs, segments)
+  if path then
+    return path
+  end
+
+  -- Without the module names of Python 3.10+ every import is looked up in the standard library
+  if env.stdlib and (env.stdlib_modules[segments[1]] or next(env.stdlib_modules) == nil) then
+    path = find_module({ env.stdlib }, segments)
+    if path then
+      return path
+    end
+  end
+  if config.get_language_config("python").follow_imports ~= false then
+    return find_module(env.site_packages, segments)
+  end
+  return nil
+end
+
+---Get the module file of a package directory
+---Packages with an `__init__.py` resolve to it, so a directory is usually a namespace package without one
+---@param dir string Canonical package directory
+---@return string[] files
+function M.package_files(dir)
+  for _, name in ipairs({ "__init__.py", "__init__.pyi" }) do
+    if vim.fn.filereadable(dir .. "/" .. name) == 1 then
+      return { utils.canonical_path(dir .. "/" .. name) }
+    end
+  end
+  return {}
+end
+
+---Check whether an import is from the standard library
+---@param import_path string Import as written
+---@param resolved string|nil Resolved path
+---@param from_file string Absolute path of the importing file
+---@return boolean
+function M.is_stdlib(import_path, resolved, from_file)
+  if vim.startswith(import_path, ".") then
+    return false
+  end
+
+  local env = get_env(utils.canonical_path(project.find_root(from_file)))
+  if resolved then
+    return env.stdlib ~= nil and vim.startswith(resolved, env.stdlib .. "/")
+  end
+  return env.stdlib_modules[import_path:match("^[^.]+")] == true
+end
+
+---Check whether an import is an installed dependency
+---@param import_path string Import as written
+---@param resolved string|nil Resolved path
+---@param from_file string Absolute path of the importing file
+---@return boolean
+function M.is_external(import_path, resolved, from_file)
+  if vim.startswith(import_path, ".") or not resolved then
+    return false
+  end
+
+  local env = get_env(utils.canonical_path(project.find_root(from_file)))
+  for _, dir in ipairs(env.site_packages) do
+    if vim.startswith(resolved, dir .. "/") then
+      return true
+    end
+  end
+  return false
+end
+
+return M
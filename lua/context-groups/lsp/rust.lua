@@ -0,0 +1,301 @@
+-- lua/context-groups/lsp/rust.lua
+-- Rust import resolution through the module tree of crates listed by `cargo metadata --offline`
+
+local config = require("context-groups.config")
+local utils = require("context-groups.utils")
+
+---@type ImportHandler
+local M = {}
+
+-- Crates of the standard distribution
+local STD_CRATES = { std = true, core = true, alloc = true, proc_macro = true, test = true }
+
+---@class RustCrate
+---@field name string Crate name as used in paths (dashes replaced by underscores)
+---@field root string Canonical path of the crate root file (lib.rs, main.rs, ...)
+---@field member boolean Whether the crate belongs to the workspace
+
+---@class RustWorkspace
+---@field root string Canonical workspace root
+---@field crates table<string, RustCrate> Library crates by name
+---@field targets RustCrate[] Crate roots of workspace members, libraries first
+
+-- Workspaces by manifest path, with the mtime of the manifest they were read at
+---@type table<string, {mtime: integer, workspace: RustWorkspace|nil}>
+local workspaces = {}
+
+---Run cargo metadata, with dependencies when they are available offline
+---@param manifest string Absolute Cargo.toml path
+---@return table|nil metadata
+local function cargo_metadata(manifest)
+  if vim.fn.executable("cargo") ~= 1 then
+    return nil
+  end
+
+  local cmd = { "cargo", "metadata", "--offline", "--format-version", "1", "--manifest-path", manifest }
+  local output = vim.fn.system(cmd)
+  if vim.v.shell_error ~= 0 then
+    -- Dependencies missing from the local cache fail the whole command
+    output = vim.fn.system(vim.list_extend(cmd, { "--no-deps" }))
+  end
+  if vim.v.shell_error ~= 0 then
+    return nil
+  end
+
+  local ok, metadata = pcall(vim.fn.json_decode, output)
+  return ok and type(metadata) == "table" and metadata or nil
+end
+
+---Read the crates of a workspace from cargo metadata
+---@param manifest string Absolute Cargo.toml path
+---@return RustWorkspace|nil workspace
+local function read_workspace(manifest)
+  local metadata = cargo_metadata(manifest)
+  if not metadata then
+    return nil
+  end
+
+  local members = {}
+  for _, id in ipairs(metadata.workspace_members or {}) do
+    members[id] = true
+  end
+
+  local workspace = { root = utils.canonical_path(metadata.workspace_root), crates = {}, targets = {} }
+  for _, package in ipairs(metadata.packages or {}) do
+    for _, target in ipairs(package.targets or {}) do
+      local crate = {
+        name = (target.name:gsub("-", "_")),
+        root = utils.canonical_path(target.src_path),
+        member = members[package.id] == true,
+      }
+      local is_lib = vim.tbl_contains(target.kind or {}, "lib")
+        or vim.tbl_contains(target.kind or {}, "proc-macro")
+        or vim.tbl_contains(target.kind or {}, "rlib")
+      -- Workspace members win over dependencies of the same name
+      local existing = workspace.crates[crate.name]
+      if is_lib and not (existing and existing.member) then
+        workspace.crates[crate.name] = crate
+      end
+      if crate.member then
+        table.insert(workspace.targets, is_lib and 1 or #workspace.targets + 1, crate)
+      end
+    end
+  end
+  return workspace
+end
+
+---Get the workspace of the nearest Cargo.toml, reading it again only when the manifest changed
+---@param from_file string Absolute file path
+---@return RustWorkspace|nil workspace
+local function find_workspace(from_file)
+  local manifest = vim.fn.findfile("Cargo.toml", vim.fn.fnamemodify(from_file, ":h") .. ";")
+  if manifest == "" then
+    return nil
+  end
+
+  manifest = vim.fn.fnamemodify(manifest, ":p")
+  local mtime = vim.fn.getftime(manifest)
+  local cached = workspaces[manifest]
+  if not cached or cached.mtime ~= mtime then
+    cached = { mtime = mtime, workspace = read_workspace(manifest) }
+    workspaces[manifest] = cached
+  end
+  return cached.workspace
+end
+
+---Find the crate root a file belongs to: the target it is the root of, or the nearest library or binary
+---@param workspace RustWorkspace Workspace
+---@param from_file string Canonical file path
+---@return RustCrate|nil crate
+local function find_crate(workspace, from_file)
+  local best
+  for _, crate in ipairs(workspace.targets) do
+    if crate.root == from_file then
+      return crate
+    end
+    local dir = vim.fn.fnamemodify(crate.root, ":h")
+    if vim.startswith(from_file, dir .. "/") and (not best or #dir > #vim.fn.fnamemodify(best.root, ":h")) then
+      best = crate
+    end
+  end
+  return best
+end
+
+---Get the directory holding the submodules of a file's module
+---Crate roots, `lib.rs`, `main.rs` and `mod.rs` files own their directory; `a/b.rs` owns `a/b/`
+---@param file string Canonical file path
+---@param crate RustCrate|nil Crate of the file
+---@return string dir
+local function module_dir(file, crate)
+  local dir = vim.fn.fnamemodify(file, ":h")
+  local filename = vim.fn.fnamemodify(file, ":t")
+  if (crate and crate.root == file) or filename == "lib.rs" or filename == "main.rs" or filename == "mod.rs" then
+    return dir
+  end
+  return dir .. "/" .. vim.fn.fnamemodify(file, ":t:r")
+end
+
+---Find the file of the longest module path below a module directory
+---Later segments may name items rather than modules, so `a::b::C` is tried as `a/b/C`, `a/b` and `a`
+---@param dir string Module directory
+---@param segments string[] Module path segments
+---@return string|nil path
+local function find_module(dir, segments)
+  for count = #segments, 1, -1 do
+    local base = dir .. "/" .. table.concat(segments, "/", 1, count)
+    for _, candidate in ipairs({ base .. ".rs", base .. "/mod.rs" }) do
+      if vim.fn.filereadable(candidate) == 1 then
+        return utils.canonical_path(candidate)
+      end
+    end
+  end
+  return nil
+end
+
+---Reduce a use tree to the module path it starts with: `a::b::{C, d::E}` and `a::b::*` become `a::b`,
+---`a::b as c` becomes `a::b`
+---@param import_path string Use argument as written
+---@return string path
+local function use_path(import_path)
+  return (import_path:gsub("%s+as%s+%S+$", ""):gsub("::%s*[{*].*$", ""):gsub("^::", ""))
+end
+
+---Join two parts of a use path, either of which may be empty
+---@param prefix string Path so far
+---@param path string Path to append
+---@return string path
+local function join_path(prefix, path)
+  if prefix == "" or path == "" then
+    return prefix .. path
+  end
+  return prefix .. "::" .. path
+end
+
+---Split a use tree into one path per leaf: `a::{b, c::{D, self}}` becomes `a::b`, `a::c::D` and `a::c`
+---Globs and renames name the path they apply to, so `a::*` and `a::b as c` become `a` and `a::b`
+---@param import_path string Use argument or declared module name
+---@return string[] paths
+function M.expand_import(import_path)
+  local paths = {}
+  local seen = {}
+
+  local function expand(prefix, tree)
+    tree = vim.trim(tree):gsub("%s+as%s+[%w_]+$", "")
+    local head, list = tree:match("^([^{]-){(.*)}$")
+    if not head then
+      local leaf = tree:gsub("::%*$", ""):gsub("^%*$", ""):gsub("^self$", "")
+      local path = join_path(prefix, leaf)
+      if path ~= "" and not seen[path] then
+        seen[path] = true
+        table.insert(paths, path)
+      end
+      return
+    end
+
+    -- Split the list on the commas outside nested braces
+    prefix = join_path(prefix, (head:gsub("::$", "")))
+    local depth = 0
+    local start = 1
+    for i = 1, #list + 1 do
+      local char = list:sub(i, i)
+      if char == "{" then
+        depth = depth + 1
+      elseif char == "}" then
+        depth = depth - 1
+      elseif (char == "," and depth == 0) or i > #list then
+        local item = list:sub(start, i - 1)
+        if item:match("%S") then
+          expand(prefix, item)
+        end
+        start = i + 1
+      end
+    end
+  end
+
+  expand("", (import_path:gsub("%s*::%s*", "::"):gsub("^::", "")))
+  return paths
+end
+
+---Resolve a Rust use path or `mod` declaration to a file
+---@param import_path string Use argument or declared module name
+---@param from_file string Absolute path of the importing file
+---@return string|nil path
+function M.resolve_import(import_path, from_file)
+  from_file = utils.canonical_path(from_file)
+  local workspace = find_workspace(from_file)
+  local crate = workspace and find_crate(workspace, from_file)
+  local segments = vim.split(use_path(import_path), "::", { plain = true, trimempty = true })
+  if #segments == 0 then
+    return nil
+  end
+
+  local first = table.remove(segments, 1)
+  if first == "crate" then
+    return crate and #segments > 0 and find_module(vim.fn.fnamemodify(crate.root, ":h"), segments) or nil
+  end
+
+  local dir = module_dir(from_file, crate)
+  if first == "self" or first == "super" then
+    if first == "super" then
+      dir = vim.fn.fnamemodify(dir, ":h")
+    end
+    while segments[1] == "super" do
+      table.remove(segments, 1)
+      dir = vim.fn.fnamemodify(dir, ":h")
+    end
+    return #segments > 0 and find_module(dir, segments) or nil
+  end
+
+  -- `mod foo;` declarations and 2015-style paths name submodules of the current module
+  local submodule = find_module(dir, { first })
+  if submodule then
+    return #segments > 0 and find_module(dir, vim.list_extend({ first }, segments)) or submodule
+  end
+
+  local dependency = workspace and workspace.crates[first]
+  if not dependency then
+    return nil
+  end
+  -- Registry sources are only read when external imports are shown
+  if not dependency.member and not config.get_import_prefs().show_external then
+    return nil
+  end
+  local crate_dir = vim.fn.fnamemodify(dependency.root, ":h")
+  return #segments > 0 and find_module(crate_dir, segments) or dependency.root
+end
+
+---Get the module file of a module directory
+---@param dir string Canonical module directory
+---@return string[] files
+function M.package_files(dir)
+  local path = dir .. "/mod.rs"
+  return vim.fn.filereadable(path) == 1 and { utils.canonical_path(path) } or {}
+end
+
+---Check whether an import is from the standard distribution
+---@param import_path string Use argument or declared module name
+---@return boolean
+function M.is_stdlib(import_path)
+  return STD_CRATES[use_path(import_path):match("^[%w_]+")] == true
+end
+
+---Check whether an import is a crate from outside the workspace
+---@param import_path string Use argument or declared module name
+---@param resolved string|nil Resolved path
+---@param from_file string Absolute path of the importing file
+---@return boolean
+function M.is_external(import_path, resolved, from_file)
+  local first = use_path(import_path):match("^[%w_]+")
+  if not first or first == "crate" or first == "self" or first == "super" then
+    return false
+  end
+
+  local workspace = find_workspace(utils.canonical_path(from_file))
+  if resolved then
+    return workspace ~= nil and not vim.startswith(resolved, workspace.root .. "/")
+  end
+  local crate = workspace and workspace.crates[first]
+  return crate ~= nil and not crate.member
+end
+
+return M
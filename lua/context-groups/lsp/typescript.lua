@@ -0,0 +1,399 @@
+-- lua/context-groups/lsp/typescript.lua
+-- TypeScript/JavaScript module resolution: tsconfig paths and baseUrl, workspace packages and node_modules
+
+local project = require("context-groups.project")
+local utils = require("context-groups.utils")
+
+---@type ImportHandler
+local M = {}
+
+-- Extensions tried for extensionless specifiers, in TypeScript's order
+local EXTENSIONS = { ".ts", ".tsx", ".d.ts", ".js", ".jsx", ".mjs", ".cjs" }
+
+-- Conditions picked from package.json `exports`, sources before declarations
+local CONDITIONS = { "import", "module", "default", "require", "node", "types" }
+
+-- Node.js built-in modules and their public subpaths
+local BUILTIN_MODULES = {}
+for _, name in ipairs({
+  "assert", "async_hooks", "buffer", "child_process", "cluster", "console", "crypto", "dgram", "diagnostics_channel",
+  "dns", "events", "fs", "http", "http2", "https", "inspector", "module", "net", "os", "path", "perf_hooks",
+  "process", "querystring", "readline", "repl", "stream", "string_decoder", "timers", "tls", "tty", "url", "util",
+  "v8", "vm", "worker_threads", "zlib", "assert/strict", "dns/promises", "fs/promises", "inspector/promises",
+  "path/posix", "path/win32", "readline/promises", "stream/consumers", "stream/promises", "stream/web",
+  "timers/promises", "util/types",
+}) do
+  BUILTIN_MODULES[name] = true
+end
+
+---@class TsConfig
+---@field base_url string|nil Absolute baseUrl
+---@field paths_base string Directory `paths` targets are relative to
+---@field paths table<string, string[]> Path mappings
+
+-- Parsed files by path, with the mtime they were read at
+---@type table<string, {mtime: integer, value: any}>
+local file_cache = {}
+
+-- Workspace packages by project root, with the mtimes of the workspace manifests they were listed at
+---@type table<string, {mtimes: string, dirs: table<string, string>}>
+local workspace_cache = {}
+
+---Decode JSON with comments and trailing commas, as tsconfig.json allows
+---@param content string JSONC content
+---@return table|nil value
+local function decode_jsonc(content)
+  local out = {}
+  local i, in_string = 1, false
+  while i <= #content do
+    local char = content:sub(i, i)
+    local pair = content:sub(i, i + 1)
+    if in_string then
+      table.insert(out, char)
+      if char == "\\" then
+        table.insert(out, content:sub(i + 1, i + 1))
+        i = i + 1
+      elseif char == '"' then
+        in_string = false
+      end
+    elseif pair == "//" then
+      i = (content:find("\n", i, true) or #content + 1) - 1
+    elseif pair == "/*" then
+      i = (content:find("*/", i + 2, true) or #content) + 1
+    else
+      in_string = char == '"'
+      table.insert(out, char)
+    end
+    i = i + 1
+  end
+
+  local json = table.concat(out):gsub(",(%s*[%]}])", "%1")
+  local ok, value = pcall(vim.fn.json_decode, json)
+  return ok and type(value) == "table" and value or nil
+end
+
+---Read a JSON(C) file, decoding it again only when it changed
+---@param path string Absolute file path
+---@return table|nil value
+local function read_json(path)
+  local mtime = vim.fn.getftime(path)
+  if mtime < 0 then
+    return nil
+  end
+
+  local cached = file_cache[path]
+  if not cached or cached.mtime ~= mtime then
+    cached = { mtime = mtime, value = decode_jsonc(utils.read_file_content(path) or "") }
+    file_cache[path] = cached
+  end
+  return cached.value
+end
+
+---Resolve a path to a module file: as is, with a TypeScript or JavaScript extension, or as a directory index
+---@param base string Absolute path without or with extension
+---@return string|nil path
+local function resolve_file(base)
+  if vim.fn.filereadable(base) == 1 and vim.fn.isdirectory(base) == 0 then
+    return utils.canonical_path(base)
+  end
+
+  local candidates = {}
+  -- TypeScript sources are imported with the extension of their output (`./foo.js` for foo.ts)
+  local stem, ext = base:match("^(.*)%.([mc]?jsx?)$")
+  if stem then
+    local ts_ext = ext:gsub("js", "ts")
+    table.insert(candidates, stem .. "." .. ts_ext)
+  end
+  for _, extension in ipairs(EXTENSIONS) do
+    table.insert(candidates, base .. extension)
+  end
+  for _, extension in ipairs(EXTENSIONS) do
+    table.insert(candidates, base .. "/index" .. extension)
+  end
+
+  for _, candidate in ipairs(candidates) do
+    if vim.fn.filereadable(candidate) == 1 then
+      return utils.canonical_path(candidate)
+    end
+  end
+  return nil
+end
+
+---Load the nearest tsconfig.json or jsconfig.json, following relative `extends`
+---@param from_file string Absolute path of the importing file
+---@return TsConfig|nil tsconfig
+local function find_tsconfig(from_file)
+  local dir = vim.fn.fnamemodify(from_file, ":h")
+  local path
+  for _, name in ipairs({ "tsconfig.json", "jsconfig.json" }) do
+    local found = vim.fn.findfile(name, dir .. ";")
+    if found ~= "" and (not path or #vim.fn.fnamemodify(found, ":p") > #path) then
+      path = vim.fn.fnamemodify(found, ":p")
+    end
+  end
+  if not path then
+    return nil
+  end
+
+  -- Options of extended configs apply unless overridden, relative to the config defining them
+  local tsconfig = { paths = {} }
+  local seen = {}
+  local paths_set, base_url_set = false, false
+  while path and not seen[path] do
+    seen[path] = true
+    local data = read_json(path) or {}
+    local options = data.compilerOptions or {}
+    local config_dir = vim.fn.fnamemodify(path, ":h")
+
+    if not base_url_set and type(options.baseUrl) == "string" then
+      tsconfig.base_url = vim.fn.simplify(config_dir .. "/" .. options.baseUrl)
+      base_url_set = true
+    end
+    if not paths_set and type(options.paths) == "table" then
+      tsconfig.paths = options.paths
+      tsconfig.paths_base = config_dir
+      paths_set = true
+    end
+
+    local extends = type(data.extends) == "string" and data.extends or nil
+    path = nil
+    if extends and extends:match("^%.%.?/") then
+      path = vim.fn.simplify(config_dir .. "/" .. extends)
+      path = vim.endswith(path, ".json") and path or path .. ".json"
+    end
+  end
+
+  tsconfig.paths_base = tsconfig.base_url or tsconfig.paths_base
+  return tsconfig
+end
+
+---Resolve an import through tsconfig `paths`, the longest matching pattern first
+---@param import_path string Import specifier
+---@param tsconfig TsConfig Loaded tsconfig
+---@return string|nil path
+local function resolve_paths(import_path, tsconfig)
+  local best, best_prefix, captured
+  for pattern in pairs(tsconfig.paths) do
+    local prefix, suffix = pattern:match("^(.-)%*(.*)$")
+    if not prefix then
+      if pattern == import_path then
+        best, best_prefix, captured = pattern, pattern, ""
+      end
+    elseif
+      vim.startswith(import_path, prefix)
+      and vim.endswith(import_path, suffix)
+      and #import_path >= #prefix + #suffix
+      and (not best_prefix or #prefix > #best_prefix)
+    then
+      best, best_prefix, captured = pattern, prefix, import_path:sub(#prefix + 1, #import_path - #suffix)
+    end
+  end
+
+  for _, target in ipairs(best and tsconfig.paths[best] or {}) do
+    local path = resolve_file(tsconfig.paths_base .. "/" .. target:gsub("%*", function()
+      return captured
+    end))
+    if path then
+      return path
+    end
+  end
+  return nil
+end
+
+---Pick the target of a package.json `exports` value for the conditions in CONDITIONS
+---@param value any Exports value: a path, a list of paths or a conditions object
+---@return string|nil target Package-relative path
+local function pick_export(value)
+  if type(value) == "string" then
+    return value
+  end
+  if type(value) ~= "table" then
+    return nil
+  end
+  if utils.is_list(value) then
+    for _, item in ipairs(value) do
+      local target = pick_export(item)
+      if target then
+        return target
+      end
+    end
+    return nil
+  end
+  for _, condition in ipairs(CONDITIONS) do
+    local target = value[condition] ~= nil and pick_export(value[condition])
+    if target then
+      return target
+    end
+  end
+  return nil
+end
+
+---Resolve a subpath of a package through package.json `exports`, `types`/`module`/`main` and index files
+---@param dir string Absolute package directory
+---@param subpath string Subpath after the package name, "" for the package itself
+---@return string|nil path
+local function resolve_package(dir, subpath)
+  local package = read_json(dir .. "/package.json") or {}
+
+  local exports = package.exports
+  if exports ~= nil then
+    local key = subpath == "" and "." or "./" .. subpath
+    local target
+    if type(exports) == "table" and not utils.is_list(exports) and vim.startswith(next(exports) or "", ".") then
+      target = pick_export(exports[key])
+      if not target then
+        -- Subpath patterns: "./*": "./src/*.ts"
+        for pattern, value in pairs(exports) do
+          local prefix, suffix = pattern:match("^(.-)%*(.*)$")
+          if prefix and vim.startswith(key, prefix) and vim.endswith(key, suffix) then
+            local captured = key:sub(#prefix + 1, #key - #suffix)
+            target = pick_export(value)
+            target = target and target:gsub("%*", function()
+              return captured
+            end)
+            break
+          end
+        end
+      end
+    elseif subpath == "" then
+      target = pick_export(exports)
+    end
+    local path = target and resolve_file(dir .. "/" .. target:gsub("^%./", ""))
+    if path then
+      return path
+    end
+  end
+
+  if subpath ~= "" then
+    return resolve_file(dir .. "/" .. subpath)
+  end
+  for _, field in ipairs({ "types", "typings", "module", "main" }) do
+    local path = type(package[field]) == "string" and resolve_file(dir .. "/" .. package[field]:gsub("^%./", ""))
+    if path then
+      return path
+    end
+  end
+  return resolve_file(dir .. "/index") or resolve_file(dir .. "/src/index")
+end
+
+---Split a bare specifier into package name and subpath: `@scope/pkg/a/b` into `@scope/pkg` and `a/b`
+---@param import_path string Bare import specifier
+---@return string name, string subpath
+local function split_package(import_path)
+  local name = import_path:match("^(@[^/]+/[^/]+)") or import_path:match("^([^/]+)")
+  return name, import_path:sub(#name + 2)
+end
+
+---List the workspace packages of a project from package.json `workspaces` or pnpm-workspace.yaml
+---@param root string Canonical project root
+---@return table<string, string> dirs Package directories by package name
+local function workspace_packages(root)
+  local mtimes = vim.fn.getftime(root .. "/package.json") .. ":" .. vim.fn.getftime(root .. "/pnpm-workspace.yaml")
+  if workspace_cache[root] and workspace_cache[root].mtimes == mtimes then
+    return workspace_cache[root].dirs
+  end
+
+  local patterns = {}
+  local workspaces = (read_json(root .. "/package.json") or {}).workspaces
+  if type(workspaces) == "table" then
+    patterns = utils.is_list(workspaces) and workspaces or workspaces.packages or {}
+  end
+  for line in (utils.read_file_content(root .. "/pnpm-workspace.yaml") or ""):gmatch("[^\n]+") do
+    local pattern = line:match("^%s*%-%s*[\"']?([^\"'#]-)[\"']?%s*$")
+    if pattern and not vim.startswith(pattern, "!") then
+      table.insert(patterns, pattern)
+    end
+  end
+
+  local dirs = {}
+  for _, pattern in ipairs(patterns) do
+    for _, dir in ipairs(vim.fn.glob(root .. "/" .. pattern, false, true)) do
+      local package = read_json(dir .. "/package.json")
+      if package and type(package.name) == "string" then
+        dirs[package.name] = utils.canonical_path(dir)
+      end
+    end
+  end
+  workspace_cache[root] = { mtimes = mtimes, dirs = dirs }
+  return dirs
+end
+
+---Resolve a JavaScript or TypeScript import specifier to a file
+---@param import_path string Import specifier
+---@param from_file string Absolute path of the importing file
+---@return string|nil path
+function M.resolve_import(import_path, from_file)
+  local dir = vim.fn.fnamemodify(from_file, ":h")
+  if import_path:match("^%.%.?/") or import_path == "." or import_path == ".." then
+    return resolve_file(vim.fn.simplify(dir .. "/" .. import_path))
+  end
+  if vim.startswith(import_path, "/") then
+    return resolve_file(import_path)
+  end
+
+  -- Aliases and baseUrl directories may shadow built-in names such as `util/format`
+  local tsconfig = find_tsconfig(from_file)
+  if tsconfig then
+    local path = resolve_paths(import_path, tsconfig)
+      or (tsconfig.base_url and resolve_file(tsconfig.base_url .. "/" .. import_path))
+    if path then
+      return path
+    end
+  end
+  if M.is_stdlib(import_path) then
+    return nil
+  end
+
+  local name, subpath = split_package(import_path)
+  local root = utils.canonical_path(project.find_root(from_file))
+  local package_dir = workspace_packages(root)[name]
+  if package_dir then
+    return resolve_package(package_dir, subpath)
+  end
+
+  -- Node resolution: node_modules of every directory up from the importing file
+  while true do
+    local candidate = dir .. "/node_modules/" .. name
+    if vim.fn.isdirectory(candidate) == 1 then
+      return resolve_package(candidate, subpath)
+    end
+    local parent = vim.fn.fnamemodify(dir, ":h")
+    if parent == dir then
+      return nil
+    end
+    dir = parent
+  end
+end
+
+---Get the module file of a package directory: its entry point or index file
+---@param dir string Canonical package directory
+---@return string[] files
+function M.package_files(dir)
+  local path = resolve_package(dir, "")
+  return path and { path } or {}
+end
+
+---Check whether an import is a Node.js built-in module
+---@param import_path string Import specifier
+---@param resolved? string|nil Resolved path; specifiers that resolved to a file are not built-in
+---@return boolean
+function M.is_stdlib(import_path, resolved)
+  if resolved then
+    return false
+  end
+  return vim.startswith(import_path, "node:") or BUILTIN_MODULES[import_path] == true
+end
+
+---Check whether an import resolves to an installed package
+---@param import_path string Import specifier
+---@param resolved string|nil Resolved path
+---@return boolean
+function M.is_external(import_path, resolved)
+  if resolved then
+    return resolved:find("/node_modules/", 1, true) ~= nil
+  end
+  -- Unresolved bare specifiers are packages that are not installed
+  return not import_path:match("^%.") and not vim.startswith(import_path, "/")
+end
+
+return M
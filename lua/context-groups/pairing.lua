@@ -0,0 +1,132 @@
+-- lua/context-groups/pairing.lua
+-- Infer test/implementation file pairs from per-language naming rules
+
+local config = require("context-groups.config")
+
+local M = {}
+
+---@class PairingRule
+---@field test string Test file name template, e.g. "{name}_test.go"
+---@field impl string Implementation file name template, e.g. "{name}.go"
+
+---Convert a file name template to an anchored Lua pattern
+---`{name}` captures any text and `{ext}` an extension; everything else is literal
+---@param template string File name template
+---@return string pattern, string[] fields Captured placeholders in order
+local function template_pattern(template)
+  local fields = {}
+  local pattern = template:gsub("[%^%$%(%)%%%.%[%]%*%+%-%?]", "%%%0"):gsub("{(%w+)}", function(field)
+    table.insert(fields, field)
+    return field == "ext" and "(%w+)" or "(.+)"
+  end)
+  return "^" .. pattern .. "$", fields
+end
+
+---Match a file name against a template
+---@param template string File name template
+---@param filename string File name
+---@return table<string, string>|nil values Placeholder values, nil when the name does not match
+local function match_template(template, filename)
+  local pattern, fields = template_pattern(template)
+  local captures = { filename:match(pattern) }
+  if #captures == 0 then
+    return nil
+  end
+
+  local values = {}
+  for i, field in ipairs(fields) do
+    values[field] = captures[i]
+  end
+  return values
+end
+
+---Fill the placeholders of a template
+---@param template string File name template
+---@param values table<string, string> Placeholder values
+---@return string filename
+local function fill_template(template, values)
+  return (template:gsub("{(%w+)}", function(field)
+    return values[field] or ""
+  end))
+end
+
+---Get the pairing rules for a file
+---@param path string File path
+---@return PairingRule[] rules
+function M.get_rules(path)
+  local filetype = vim.filetype.match({ filename = path })
+  local rules = config.get().pairing.rules or {}
+  return filetype and rules[filetype] or {}
+end
+
+---List the directories a paired file may live in
+---Tests sit next to their implementation, or in a test directory mirroring it (`tests/pkg/test_x.py` for `pkg/x.py`)
+---@param dir string Directory of the file, relative to the root ("." for the root itself)
+---@param is_test boolean Whether the file is the test
+---@return string[] dirs Relative directories, nearest first
+local function candidate_dirs(dir, is_test)
+  local dirs = { dir }
+  local segments = dir == "." and {} or vim.split(dir, "/")
+
+  for _, test_dir in ipairs(config.get().pairing.test_dirs or {}) do
+    if is_test then
+      -- Drop the test directory from the path, optionally placing the rest under src/
+      for i, segment in ipairs(segments) do
+        if segment == test_dir then
+          local rest = vim.list_extend(vim.list_slice(segments, 1, i - 1), vim.list_slice(segments, i + 1))
+          local mirrored = #rest > 0 and table.concat(rest, "/") or "."
+          table.insert(dirs, mirrored)
+          table.insert(dirs, mirrored == "." and "src" or "src/" .. mirrored)
+        end
+      end
+    elseif dir == "." then
+      table.insert(dirs, test_dir)
+    else
+      table.insert(dirs, dir .. "/" .. test_dir)
+      local mirrored = (dir .. "/"):gsub("^src/", ""):gsub("/$", "")
+      table.insert(dirs, mirrored == "" and test_dir or test_dir .. "/" .. mirrored)
+    end
+  end
+
+  return dirs
+end
+
+---Find the existing files paired with a file: its implementation when it is a test, its tests otherwise
+---@param path string Absolute file path
+---@param root string Canonical project root
+---@return string[] paired Absolute paths of existing paired files
+function M.find(path, root)
+  local cfg = config.get().pairing
+  if not cfg or not cfg.enabled or not vim.startswith(path, root .. "/") then
+    return {}
+  end
+
+  local rel_path = path:sub(#root + 2)
+  local dir = vim.fn.fnamemodify(rel_path, ":h")
+  local filename = vim.fn.fnamemodify(rel_path, ":t")
+
+  local paired = {}
+  local seen = { [path] = true }
+  for _, rule in ipairs(M.get_rules(path)) do
+    -- A test name also matches the broader implementation template, so tests are checked first
+    local values = match_template(rule.test, filename)
+    local is_test = values ~= nil
+    local target = is_test and rule.impl or rule.test
+    values = values or match_template(rule.impl, filename)
+
+    if values then
+      local name = fill_template(target, values)
+      for _, candidate_dir in ipairs(candidate_dirs(dir, is_test)) do
+        local candidate = root .. "/" .. (candidate_dir == "." and "" or candidate_dir .. "/") .. name
+        if not seen[candidate] and vim.fn.filereadable(candidate) == 1 then
+          seen[candidate] = true
+          table.insert(paired, candidate)
+        end
+      end
+    end
+  end
+
+  return paired
+end
+
+return M
@@ -3,6 +3,9 @@
 
 local config = require("context-groups.config")
 local core = require("context-groups.core")
+local entries = require("context-groups.entries")
+local groups = require("context-groups.groups")
+local history = require("context-groups.history")
 
 local finders = require("telescope.finders")
 local pickers = require("telescope.pickers")
@@ -21,6 +24,34 @@ local function create_previewer()
   return previewers.new_buffer_previewer({
     title = "File Preview",
     define_preview = function(self, entry)
+      -- Included named groups preview their files
+      if entry.reference then
+        local files = core.get_context_files(source_bufnr, { group = entry.reference, relative = true })
+        vim.api.nvim_buf_set_lines(self.state.bufnr, 0, -1, false, #files > 0 and files or { "(empty)" })
+        return
+      end
+
+      -- Rules preview the files they currently match
+      if entry.rule then
+        local files = {}
+        for _, item in ipairs(core.get_context_entries(source_bufnr)) do
+          if item.rule == entry.rule then
+            table.insert(files, vim.fn.fnamemodify(item.path, ":~:."))
+          end
+        end
+        vim.api.nvim_buf_set_lines(self.state.bufnr, 0, -1, false, #files > 0 and files or { "(no matches)" })
+        return
+      end
+
+      -- Directories, such as Go packages, preview their files
+      if entry.path and vim.fn.isdirectory(entry.path) == 1 then
+        local files = vim.tbl_map(function(file)
+          return vim.fn.fnamemodify(file, ":~:.")
+        end, core.get_directory_files(entry.path))
+        vim.api.nvim_buf_set_lines(self.state.bufnr, 0, -1, false, #files > 0 and files or { "(empty)" })
+        return
+      end
+
       local content = require("context-groups.utils").read_file_content(entry.path)
       if not content then
         vim.api.nvim_buf_set_lines(self.state.bufnr, 0, -1, false, { "File not readable" })
@@ -30,6 +61,11 @@ local function create_previewer()
       local lines = vim.split(content, "\n")
       local max_lines = config.get().max_preview_lines or 500
 
+      -- Range and symbol entries preview only their lines
+      if entry.range then
+        lines = vim.list_slice(lines, entry.range[1], entry.range[2])
+      end
+
       -- Limit number of preview lines
       if #lines > max_lines then
         lines = vim.list_slice(lines, 1, max_lines)
@@ -66,14 +102,47 @@ function M.show_file_picker()
 
   local project_items = core.get_project_items(source_bufnr)
 
+  -- Offer the buffer's test or implementation first, then named groups for inclusion ahead of files
+  local root = core.get_current_root(source_bufnr)
+  local active_group = core.get_active_group(source_bufnr)
+  local paired = {}
+  local items = {}
+  for _, path in ipairs(core.get_paired_files(source_bufnr)) do
+    paired[path] = true
+    table.insert(items, path)
+  end
+  for _, group in ipairs(root and groups.list(root) or {}) do
+    if group.name ~= active_group then
+      table.insert(items, groups.key(group.name))
+    end
+  end
+  for _, item in ipairs(project_items) do
+    if not paired[item] then
+      table.insert(items, item)
+    end
+  end
+
   pickers
     .new(config.get().telescope_theme, {
       prompt_title = "Add to Context Group",
       finder = finders.new_table({
-        results = project_items,
+        results = items,
         entry_maker = function(entry)
+          if groups.is_named_key(entry) then
+            local label = groups.label(groups.name_from_key(entry))
+            return {
+              value = entry,
+              display = label .. " [group]",
+              ordinal = label,
+              reference = groups.name_from_key(entry),
+            }
+          end
+
           local is_directory = vim.fn.isdirectory(entry) == 1
           local display_name = vim.fn.fnamemodify(entry, ":~:.")
+          if paired[entry] then
+            display_name = display_name .. " [pair]"
+          end
 
           return {
             value = entry,
@@ -88,52 +157,28 @@ function M.show_file_picker()
       sorter = conf.generic_sorter({}),
       attach_mappings = function(prompt_bufnr, map)
         -- Add file(s) to context group
-        local function add_files(close)
+        local function add_files(close, scope)
+          local opts = { scope = scope }
           local selection = action_state.get_selected_entry()
 
           if selection then
-            if selection.is_directory then
-              -- Get all files in directory
-              local files = core.get_directory_files(selection.value)
-
-              -- Always exclude the source file (the file that triggered the picker)
-              local source_file = vim.api.nvim_buf_get_name(source_bufnr)
-              if source_file and source_file ~= "" then
-                files = vim.tbl_filter(function(file)
-                  return file ~= source_file
-                end, files)
+            if selection.reference then
+              if core.add_group_reference(selection.reference, source_bufnr, opts) then
+                vim.notify(string.format("Included group %s in context group", selection.reference))
               end
-
-              if #files > 0 then
-                local result = core.add_multiple_context_files(files, source_bufnr)
-                if result.success then
-                  vim.notify(
-                    string.format(
-                      "Added %d files from %s (skipped %d duplicates)",
-                      result.added,
-                      selection.display,
-                      result.skipped
-                    )
-                  )
-                  if #result.errors > 0 then
-                    vim.notify("Errors: " .. table.concat(result.errors, ", "), vim.log.levels.WARN)
-                  end
-                else
-                  vim.notify(
-                    "Failed to add directory files: " .. table.concat(result.errors, ", "),
-                    vim.log.levels.ERROR
-                  )
-                end
-              else
-                vim.notify("No files found in directory: " .. selection.display, vim.log.levels.WARN)
+            elseif selection.is_directory then
+              -- Directories are stored as live rules so files added later are picked up
+              local rule = vim.fn.fnamemodify(selection.value, ":p")
+              if core.add_context_rule(rule, source_bufnr, opts) then
+                vim.notify(string.format("Added directory %s to context group", selection.display))
               end
             else
               -- For individual files, check if it's not the source file
               local source_file = vim.api.nvim_buf_get_name(source_bufnr)
-              if selection.value == source_file then
+              if selection.value == source_file and not core.get_active_group(source_bufnr) then
                 vim.notify("Cannot add the current file to its own context group", vim.log.levels.WARN)
               else
-                local success = core.add_context_file(selection.value, source_bufnr)
+                local success = core.add_context_file(selection.value, source_bufnr, opts)
                 if success then
                   vim.notify(string.format("Added %s to context group", selection.display))
                 end
@@ -156,6 +201,183 @@ function M.show_file_picker()
           add_files(false)
         end)
 
+        -- Add to the shared (repo-committed) group and close
+        map("i", "<C-s>", function()
+          add_files(true, "shared")
+        end)
+
+        -- Add the prompt text as a glob rule, e.g. src/api/**/*.go or !**/*_test.go
+        map("i", "<C-g>", function()
+          local rule = vim.trim(action_state.get_current_line())
+          if rule ~= "" and core.add_context_rule(rule, source_bufnr) then
+            vim.notify(string.format("Added rule %s to context group", rule))
+            actions.close(prompt_bufnr)
+          end
+        end)
+
+        return true
+      end,
+    })
+    :find()
+end
+
+-- Show picker for adding the files imported by the current buffer
+function M.show_imports_picker()
+  -- Store the source buffer number
+  source_bufnr = vim.api.nvim_get_current_buf()
+
+  local prefs = config.get_import_prefs()
+  local imports = require("context-groups.lsp").get_imports(source_bufnr)
+
+  pickers
+    .new(config.get().telescope_theme, {
+      prompt_title = string.format(
+        "Add Imports (stdlib %s, external %s)",
+        prefs.show_stdlib and "shown" or "hidden",
+        prefs.show_external and "shown" or "hidden"
+      ),
+      finder = finders.new_table({
+        results = imports,
+        entry_maker = function(import)
+          local target = import.path and vim.fn.fnamemodify(import.path, ":~:.") or "(unresolved)"
+          local display = import.name .. " -> " .. target
+          if import.related and #import.related > 0 then
+            display = display .. " (+" .. #import.related .. ")"
+          end
+          if import.kind ~= "project" then
+            display = display .. " [" .. import.kind .. "]"
+          end
+          return {
+            value = import.name,
+            display = display,
+            ordinal = import.name .. " " .. target,
+            path = import.path,
+            kind = import.kind,
+            related = import.related,
+          }
+        end,
+      }),
+      previewer = create_previewer(),
+      sorter = conf.generic_sorter({}),
+      attach_mappings = function(prompt_bufnr, map)
+        -- Add the selected imports, or the one under the cursor, to the context group
+        local function add_imports(close)
+          local selections = action_state.get_current_picker(prompt_bufnr):get_multi_selection()
+          if #selections == 0 then
+            selections = { action_state.get_selected_entry() }
+          end
+
+          local added = 0
+          for _, selection in ipairs(selections) do
+            if not selection.path then
+              vim.notify("Cannot resolve import " .. selection.value, vim.log.levels.WARN)
+            elseif vim.fn.isdirectory(selection.path) == 1 then
+              -- Packages are stored as live directory rules
+              if core.add_context_rule(vim.fn.fnamemodify(selection.path, ":p"), source_bufnr) then
+                added = added + 1
+              end
+            elseif core.add_context_file(selection.path, source_bufnr) then
+              added = added + 1
+            end
+            -- Files that belong with the import, such as the source file of a header
+            for _, path in ipairs(selection.related or {}) do
+              if core.add_context_file(path, source_bufnr) then
+                added = added + 1
+              end
+            end
+          end
+
+          if added > 0 then
+            vim.notify(string.format("Added %d imports to context group", added))
+          end
+          if close then
+            actions.close(prompt_bufnr)
+          end
+        end
+
+        -- Add and close
+        map("i", "<CR>", function()
+          add_imports(true)
+        end)
+
+        -- Add and continue
+        map("i", "<C-Space>", function()
+          add_imports(false)
+        end)
+
+        -- Toggle standard library imports
+        map("i", "<C-t>", function()
+          config.update_import_prefs({ show_stdlib = not prefs.show_stdlib })
+          refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, M.show_imports_picker)
+        end)
+
+        -- Toggle external dependencies
+        map("i", "<C-e>", function()
+          config.update_import_prefs({ show_external = not prefs.show_external })
+          refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, M.show_imports_picker)
+        end)
+
+        return true
+      end,
+    })
+    :find()
+end
+
+-- Show the dependency closure of the current buffer as a tree before adding it
+---@param opts? {depth: integer} Options {depth: Maximum import hops (default config.import_graph.depth)}
+function M.show_dependencies_picker(opts)
+  -- Store the source buffer number
+  source_bufnr = vim.api.nvim_get_current_buf()
+
+  local depth = opts and opts.depth or (config.get().import_graph or {}).depth or 2
+  local source_file = require("context-groups.utils").canonical_path(vim.api.nvim_buf_get_name(source_bufnr))
+  local nodes, truncated = core.get_dependencies(source_bufnr, { depth = depth })
+  nodes = require("context-groups.graph").tree_order(source_file, nodes)
+
+  pickers
+    .new(config.get().telescope_theme, {
+      prompt_title = string.format("Add Dependencies (depth %d%s)", depth, truncated and ", truncated" or ""),
+      finder = finders.new_table({
+        results = nodes,
+        entry_maker = function(node)
+          local file = vim.fn.fnamemodify(node.path, ":~:.")
+          local importer = vim.fn.fnamemodify(node.imported_by, ":~:.")
+          local reason = string.format("imported by %s as %s", importer, node.import)
+          return {
+            value = node.path,
+            display = string.rep("  ", node.depth - 1) .. file .. " (" .. reason .. ")",
+            ordinal = file,
+            path = node.path,
+          }
+        end,
+      }),
+      previewer = create_previewer(),
+      sorter = conf.generic_sorter({}),
+      attach_mappings = function(prompt_bufnr, map)
+        -- Add the selected dependencies, or the whole closure when nothing is selected
+        map("i", "<CR>", function()
+          local selections = action_state.get_current_picker(prompt_bufnr):get_multi_selection()
+          local files = #selections > 0 and vim.tbl_map(function(selection)
+            return selection.path
+          end, selections) or nil
+          actions.close(prompt_bufnr)
+          core.add_dependencies(source_bufnr, { depth = depth, files = files })
+        end)
+
+        -- Follow imports one hop further
+        map("i", "<C-l>", function()
+          refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, function()
+            M.show_dependencies_picker({ depth = depth + 1 })
+          end)
+        end)
+
+        -- Follow imports one hop less
+        map("i", "<C-h>", function()
+          refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, function()
+            M.show_dependencies_picker({ depth = math.max(depth - 1, 1) })
+          end)
+        end)
+
         return true
       end,
     })
@@ -163,34 +385,181 @@ function M.show_file_picker()
 end
 
 -- Show current context group
-function M.show_context_group()
+---@param opts? {selected: string} Options {selected: Stored entry or rule to select initially}
+function M.show_context_group(opts)
   -- Store the source buffer number
   source_bufnr = vim.api.nvim_get_current_buf()
+  if not core.has_context_group(source_bufnr) then
+    vim.notify("No file open and no named context group active", vim.log.levels.WARN)
+    return
+  end
+
+  local active_group = core.get_active_group(source_bufnr)
+
+  -- Included groups and rules are listed first so they can be removed as a whole
+  local results = core.get_group_rules(source_bufnr)
+  vim.list_extend(results, core.get_context_entries(source_bufnr))
 
-  local context_files = core.get_context_files(source_bufnr)
+  -- Keep the moved entry selected when the picker is reopened
+  local selection_index
+  for index, entry in ipairs(results) do
+    if opts and opts.selected and entry.value == opts.selected and not entry.group then
+      selection_index = index
+      break
+    end
+  end
 
   pickers
     .new(config.get().telescope_theme, {
-      prompt_title = "Context Group",
+      prompt_title = active_group and ("Context Group: " .. active_group) or "Context Group",
+      default_selection_index = selection_index,
       finder = finders.new_table({
-        results = context_files,
+        results = results,
         entry_maker = function(entry)
+          if entry.kind then
+            local tags = { group = " [group]", pattern = " [rule]", exclude = " [exclude]" }
+            local display = entry.label .. tags[entry.kind]
+            if entry.scope == "shared" then
+              display = display .. " [shared]"
+            end
+            if entry.priority then
+              display = display .. string.format(" [p%d]", entry.priority)
+            end
+            if entry.note then
+              display = display .. " -- " .. entry.note
+            end
+            return {
+              value = entry.value,
+              display = display,
+              ordinal = entry.label,
+              reference = entry.kind == "group" and groups.name_from_key(entry.value) or nil,
+              rule = entry.value,
+              scope = entry.scope,
+              note = entry.note,
+              priority = entry.priority,
+            }
+          end
+
+          local display = entries.format({
+            file = vim.fn.fnamemodify(entry.path, ":~:."),
+            range = entry.range,
+            symbol = entry.symbol,
+          })
+          if entry.scope == "shared" then
+            display = display .. " [shared]"
+          end
+          -- Show which included group or rule an inherited entry comes from
+          if entry.group then
+            display = display .. " (from " .. groups.label(entry.group) .. ")"
+          elseif entry.rule then
+            display = display .. " (from " .. core.get_rule(entry.rule) .. ")"
+          elseif entry.paired then
+            display = display .. " (paired)"
+          end
+          if entry.priority then
+            display = display .. string.format(" [p%d]", entry.priority)
+          end
+          if entry.note then
+            display = display .. " -- " .. entry.note
+          end
+
           return {
-            value = entry,
-            display = vim.fn.fnamemodify(entry, ":~:."),
-            ordinal = entry,
-            path = entry,
+            value = entry.path,
+            display = display,
+            ordinal = display,
+            path = entry.path,
+            scope = entry.scope,
+            group = entry.group,
+            from_rule = entry.rule,
+            paired = entry.paired,
+            stored = entry.value,
+            range = entry.symbol and core.get_entry_range(entry) or entry.range,
+            note = entry.note,
+            priority = entry.priority,
           }
         end,
       }),
       previewer = create_previewer(),
       sorter = conf.generic_sorter({}),
       attach_mappings = function(prompt_bufnr, map)
+        -- Get the stored entry or rule of a selection; inherited and rule-matched files have none
+        local function get_stored(selection)
+          if not selection or selection.group or selection.from_rule then
+            return nil
+          end
+          return selection.rule or selection.stored
+        end
+
+        -- Move the selected entry or rule within its group
+        local function move(direction)
+          local selection = action_state.get_selected_entry()
+          local stored = get_stored(selection)
+          if not stored then
+            vim.notify("Only entries of this group can be moved", vim.log.levels.WARN)
+            return
+          end
+          if core.move_entry(stored, direction, source_bufnr, { scope = selection.scope }) then
+            refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, function()
+              M.show_context_group({ selected = stored })
+            end)
+          end
+        end
+
+        map("i", "<M-k>", function()
+          move("up")
+        end)
+
+        map("i", "<M-j>", function()
+          move("down")
+        end)
+
+        -- Pin to the top of the group
+        map("i", "<M-t>", function()
+          move("top")
+        end)
+
+        -- Set priority; lower priorities are dropped first when an export exceeds the size budget
+        map("i", "<M-p>", function()
+          local selection = action_state.get_selected_entry()
+          local stored = get_stored(selection)
+          if not stored then
+            vim.notify("Set the priority on the group or rule this file comes from", vim.log.levels.WARN)
+            return
+          end
+
+          actions.close(prompt_bufnr)
+          local default = tostring(selection.priority or 0)
+          vim.ui.input({ prompt = "Priority: ", default = default }, function(input)
+            local priority = tonumber(input)
+            if input and not priority then
+              vim.notify("Priority must be a number", vim.log.levels.ERROR)
+            elseif priority then
+              core.set_entry_priority(stored, math.floor(priority), source_bufnr, { scope = selection.scope })
+            end
+            M.show_context_group({ selected = stored })
+          end)
+        end)
+
         -- Remove file from context group
         map("i", "<C-d>", function()
           local selection = action_state.get_selected_entry()
-          if selection then
-            local success = core.remove_context_file(selection.value, source_bufnr)
+          if selection and selection.group then
+            vim.notify(
+              string.format("%s comes from group %s; remove it there", selection.value, selection.group),
+              vim.log.levels.WARN
+            )
+          elseif selection then
+            local success
+            if selection.rule then
+              success = core.remove_context_rule(selection.rule, source_bufnr, { scope = selection.scope })
+            elseif selection.from_rule or selection.paired then
+              -- Files matched by a rule or paired with the buffer are excluded instead of removed
+              success = core.exclude_file(selection.value, source_bufnr, { scope = selection.scope })
+            elseif selection.stored then
+              success = core.remove_context_rule(selection.stored, source_bufnr, { scope = selection.scope })
+            else
+              success = core.remove_context_file(selection.value, source_bufnr, { scope = selection.scope })
+            end
             if success then
               vim.notify(string.format("Removed %s from context group", selection.display))
               -- Get current mode
@@ -200,19 +569,42 @@ function M.show_context_group()
           end
         end)
 
+        -- Edit the note of an entry or rule
+        map("i", "<C-e>", function()
+          local selection = action_state.get_selected_entry()
+          local stored = get_stored(selection)
+          if not stored then
+            if selection then
+              vim.notify("Edit the note on the group or rule this file comes from", vim.log.levels.WARN)
+            end
+            return
+          end
+
+          actions.close(prompt_bufnr)
+          vim.ui.input({ prompt = "Note: ", default = selection.note or "" }, function(note)
+            if note then
+              core.set_entry_note(stored, note, source_bufnr, { scope = selection.scope })
+            end
+            M.show_context_group({ selected = stored })
+          end)
+        end)
+
         -- Open file in split
         map("i", "<C-v>", function()
           local selection = action_state.get_selected_entry()
-          if selection then
+          if selection and selection.path then
             actions.close(prompt_bufnr)
             vim.cmd("vsplit " .. vim.fn.fnameescape(selection.value))
+            if selection.range then
+              vim.api.nvim_win_set_cursor(0, { selection.range[1], 0 })
+            end
           end
         end)
 
         -- Copy path to clipboard
         map("i", "<C-y>", function()
           local selection = action_state.get_selected_entry()
-          if selection then
+          if selection and selection.path then
             vim.fn.setreg("+", selection.value)
             vim.notify("Copied path to clipboard")
           end
@@ -222,9 +614,9 @@ function M.show_context_group()
         map("i", "<C-x>", function()
           local success = core.clear_context_group(source_bufnr)
           if success then
-            vim.notify("Cleared all files from context group")
+            vim.notify("Cleared all files from context group (:ContextGroupUndo restores them)")
             actions.close(prompt_bufnr)
-          else
+          elseif success == false then
             vim.notify("Failed to clear context group", vim.log.levels.ERROR)
           end
         end)
@@ -235,4 +627,203 @@ function M.show_context_group()
     :find()
 end
 
+-- Show named context groups of the current project
+function M.show_named_groups()
+  -- Store the source buffer number
+  source_bufnr = vim.api.nvim_get_current_buf()
+
+  local root = core.get_current_root(source_bufnr)
+  if not root then
+    vim.notify("No valid file path found", vim.log.levels.WARN)
+    return
+  end
+
+  pickers
+    .new(config.get().telescope_theme, {
+      prompt_title = "Named Context Groups",
+      finder = finders.new_table({
+        results = groups.list(root),
+        entry_maker = function(entry)
+          local display = string.format("%s%s (%d files)", entry.active and "* " or "  ", entry.name, entry.count)
+          if vim.tbl_contains(entry.scopes, "shared") then
+            display = display .. " [shared]"
+          end
+
+          return {
+            value = entry.name,
+            display = display,
+            ordinal = entry.name,
+          }
+        end,
+      }),
+      previewer = previewers.new_buffer_previewer({
+        title = "Group Files",
+        define_preview = function(self, entry)
+          local files = core.get_context_files(source_bufnr, { group = entry.value, relative = true })
+          vim.api.nvim_buf_set_lines(self.state.bufnr, 0, -1, false, #files > 0 and files or { "(empty)" })
+        end,
+      }),
+      sorter = conf.generic_sorter({}),
+      attach_mappings = function(prompt_bufnr, map)
+        -- Activate group
+        map("i", "<CR>", function()
+          local selection = action_state.get_selected_entry()
+          actions.close(prompt_bufnr)
+          if selection and groups.activate(root, selection.value) then
+            vim.notify("Activated context group " .. selection.value)
+          end
+        end)
+
+        -- Create group named after the prompt text
+        map("i", "<C-n>", function()
+          local name = vim.trim(action_state.get_current_line())
+          if groups.create(root, name) then
+            vim.notify("Created context group " .. name)
+            refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, M.show_named_groups)
+          end
+        end)
+
+        -- Rename group
+        map("i", "<C-r>", function()
+          local selection = action_state.get_selected_entry()
+          if not selection then
+            return
+          end
+          actions.close(prompt_bufnr)
+          vim.ui.input({ prompt = "New group name: ", default = selection.value }, function(new_name)
+            if new_name and new_name ~= selection.value and groups.rename(root, selection.value, new_name) then
+              vim.notify(string.format("Renamed context group %s to %s", selection.value, new_name))
+            end
+            M.show_named_groups()
+          end)
+        end)
+
+        -- Delete group
+        map("i", "<C-d>", function()
+          local selection = action_state.get_selected_entry()
+          if selection and groups.delete(root, selection.value) then
+            vim.notify("Deleted context group " .. selection.value)
+            refresh_picker(prompt_bufnr, vim.api.nvim_get_mode().mode, M.show_named_groups)
+          end
+        end)
+
+        -- Deactivate the active group
+        map("i", "<C-x>", function()
+          groups.deactivate(root)
+          vim.notify("Buffers use their own context groups again")
+          actions.close(prompt_bufnr)
+        end)
+
+        return true
+      end,
+    })
+    :find()
+end
+
+-- Describe the changes of a history entry as diff lines
+---@param entry HistoryEntry History entry
+---@return string[] lines
+local function describe_changes(entry)
+  local lines = {}
+  for _, change in ipairs(entry.changes) do
+    table.insert(lines, string.format("%s [%s]", change.key, change.scope))
+
+    local before, after = {}, {}
+    for _, item in ipairs(change.before or {}) do
+      before[vim.inspect(item)] = true
+    end
+    for _, item in ipairs(change.after or {}) do
+      after[vim.inspect(item)] = true
+      if not before[vim.inspect(item)] then
+        table.insert(lines, "+ " .. entries.spec(item))
+      end
+    end
+    for _, item in ipairs(change.before or {}) do
+      if not after[vim.inspect(item)] then
+        table.insert(lines, "- " .. entries.spec(item))
+      end
+    end
+    if change.after == nil then
+      table.insert(lines, "  (group deleted)")
+    end
+    table.insert(lines, "")
+  end
+  return lines
+end
+
+-- Show the edit history of the current project's context groups
+function M.show_history()
+  -- Store the source buffer number
+  source_bufnr = vim.api.nvim_get_current_buf()
+
+  local root = core.get_current_root(source_bufnr)
+  if not root then
+    vim.notify("No valid file path found", vim.log.levels.WARN)
+    return
+  end
+
+  local history_entries, position = history.list(root)
+
+  -- Newest first; position 0 is the state before the oldest recorded edit
+  local results = {}
+  for index = #history_entries, 1, -1 do
+    table.insert(results, { position = index, entry = history_entries[index] })
+  end
+  table.insert(results, { position = 0 })
+
+  pickers
+    .new(config.get().telescope_theme, {
+      prompt_title = "Context Group History",
+      finder = finders.new_table({
+        results = results,
+        entry_maker = function(item)
+          local marker = item.position == position and "* " or "  "
+          local display
+          if item.entry then
+            display = string.format("%s%s  %s", marker, os.date("%H:%M:%S", item.entry.time), item.entry.label)
+            if item.position > position then
+              display = display .. " (undone)"
+            end
+          else
+            display = marker .. "Oldest recorded state"
+          end
+
+          return {
+            value = item.position,
+            display = display,
+            ordinal = display,
+            entry = item.entry,
+          }
+        end,
+      }),
+      previewer = previewers.new_buffer_previewer({
+        title = "Changes",
+        define_preview = function(self, item)
+          local lines = item.entry and describe_changes(item.entry) or { "(state before the first recorded edit)" }
+          vim.api.nvim_buf_set_lines(self.state.bufnr, 0, -1, false, lines)
+          vim.bo[self.state.bufnr].filetype = "diff"
+        end,
+      }),
+      sorter = conf.generic_sorter({}),
+      attach_mappings = function(prompt_bufnr, map)
+        -- Restore the state right after the selected edit
+        map("i", "<CR>", function()
+          local selection = action_state.get_selected_entry()
+          actions.close(prompt_bufnr)
+          if selection then
+            local label = selection.entry and selection.entry.label or "oldest state"
+            if history.restore(root, selection.value) then
+              vim.notify("Restored context groups to " .. label)
+            else
+              vim.notify("Could not restore context groups to " .. label, vim.log.levels.ERROR)
+            end
+          end
+        end)
+
+        return true
+      end,
+    })
+    :find()
+end
+
 return M
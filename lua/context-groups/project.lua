@@ -61,6 +61,96 @@ function M.get_relative_path(path)
   return utils.get_relative_path(path, root)
 end
 
+---Get a stable identifier for a project that survives clones and moves
+---@param root string Project root directory
+---@return string|nil id Hash of the oldest root commit, nil outside git repositories
+function M.get_project_id(root)
+  local commits = vim.fn.systemlist({ "git", "-C", root, "rev-list", "--max-parents=0", "HEAD" })
+  if vim.v.shell_error ~= 0 or #commits == 0 then
+    return nil
+  end
+
+  -- Repositories may have several root commits; pick one deterministically
+  table.sort(commits)
+  return commits[1]
+end
+
+---Detect where missing files were renamed to
+---Uses git rename detection on staged changes and recent commits, then matches the content the missing files had
+---at HEAD against untracked files, which catches renames done outside git
+---@param root string Project root directory
+---@param missing string[] Missing paths relative to the root
+---@return table<string, string> renames New relative path by missing relative path
+function M.detect_renames(root, missing)
+  local found = {}
+  if #missing == 0 then
+    return found
+  end
+  vim.fn.systemlist({ "git", "-C", root, "rev-parse", "--is-inside-work-tree" })
+  if vim.v.shell_error ~= 0 then
+    return found
+  end
+
+  -- Renames are listed newest first, so chains resolve to the latest name
+  local renames = {}
+  local function collect(cmd)
+    for _, line in ipairs(vim.fn.systemlist(cmd)) do
+      local old, new = line:match("^R%d*\t([^\t]+)\t([^\t]+)$")
+      if old and not renames[old] then
+        renames[old] = new
+      end
+    end
+  end
+  collect({ "git", "-C", root, "diff", "--cached", "--name-status", "-M", "--relative", "HEAD" })
+  collect({ "git", "-C", root, "log", "-M", "--diff-filter=R", "--name-status", "--relative", "--format=", "-n200" })
+
+  local unresolved = {}
+  for _, path in ipairs(missing) do
+    local new = renames[path]
+    for _ = 1, 20 do
+      if not new or not renames[new] then
+        break
+      end
+      new = renames[new]
+    end
+
+    if new and vim.fn.filereadable(root .. "/" .. new) == 1 then
+      found[path] = new
+    else
+      table.insert(unresolved, path)
+    end
+  end
+
+  if #unresolved == 0 then
+    return found
+  end
+
+  -- Match the blobs the missing files had at HEAD against untracked files
+  local blobs = {}
+  local tree = vim.fn.systemlist(vim.list_extend({ "git", "-C", root, "ls-tree", "-r", "HEAD", "--" }, unresolved))
+  for _, line in ipairs(tree) do
+    local blob, path = line:match("^%d+ blob (%x+)\t(.+)$")
+    if blob then
+      blobs[blob] = path
+    end
+  end
+
+  local untracked = vim.fn.systemlist({ "git", "-C", root, "ls-files", "--others", "--exclude-standard" })
+  if next(blobs) == nil or #untracked == 0 or #untracked > 1000 then
+    return found
+  end
+
+  local hashes = vim.fn.systemlist(vim.list_extend({ "git", "-C", root, "hash-object", "--" }, untracked))
+  for i, hash in ipairs(hashes) do
+    local path = blobs[hash]
+    if path and not found[path] then
+      found[path] = untracked[i]
+    end
+  end
+
+  return found
+end
+
 ---Check if path is in current project
 ---@param path string File path to check
 ---@return boolean is_in_project
@@ -1,62 +1,431 @@
 -- lua/context-groups/storage.lua
 -- Storage functionality extracted from core.lua
 
+local branches = require("context-groups.branches")
 local config = require("context-groups.config")
+local project = require("context-groups.project")
 local utils = require("context-groups.utils")
 
 local M = {}
 
+-- Keys of named groups start with this prefix; all other keys are file paths. Stored paths never contain `//`,
+-- while `@` starts scoped package paths such as `node_modules/@scope/pkg`
+M.NAMED_GROUP_PREFIX = "group://"
+
+-- Stored rules (globs, directories and exclusions) start with this prefix; stored paths never contain `//`
+M.RULE_PREFIX = "rule://"
+
+local uv = vim.uv or vim.loop
+
+-- How long to wait for another instance to release the lock file
+local LOCK_TIMEOUT_MS = 2000
+-- Lock files older than this are considered left behind by a crashed instance
+local LOCK_STALE_MS = 10000
+
+-- Current storage file format version
+local SCHEMA_VERSION = 3
+
+---Rewrite every stored value of a set of groups in place, keeping the metadata of table items
+---@param groups table Stored groups
+---@param rewrite fun(value: string): string
+local function rewrite_values(groups, rewrite)
+  for _, group in pairs(type(groups) == "table" and groups or {}) do
+    for i, item in ipairs(type(group) == "table" and group or {}) do
+      if type(item) == "string" then
+        group[i] = rewrite(item)
+      elseif type(item) == "table" and type(item.entry) == "string" then
+        item.entry = rewrite(item.entry)
+      end
+    end
+  end
+end
+
+-- Migrations upgrading a decoded file from version N-1 to N, applied in order
+-- Each migration also receives the version the file had before the upgrade started
+---@type table<integer, fun(decoded: table, from: integer): table>
+local migrations = {
+  -- 1: groups move under a `groups` key next to file metadata (project root, id)
+  [1] = function(decoded)
+    return { groups = decoded }
+  end,
+  -- 2: rules carry a prefix instead of being told from files by their glob characters
+  -- Version 0 files predate rules and hold only file paths, such as `pages/[id].tsx`
+  [2] = function(decoded, from)
+    if from == 0 then
+      return decoded
+    end
+    rewrite_values(decoded.groups, function(value)
+      local is_rule = vim.startswith(value, "!") or vim.endswith(value, "/") or value:find("[%*%?%[]") ~= nil
+      return is_rule and M.RULE_PREFIX .. value or value
+    end)
+    return decoded
+  end,
+  -- 3: named groups move from the `@` prefix to one paths cannot start with; `@scope/...` paths stay files
+  [3] = function(decoded)
+    local function rewrite(value)
+      return value:match("^@[%w][%w%._%-]*$") and M.NAMED_GROUP_PREFIX .. value:sub(2) or value
+    end
+
+    rewrite_values(decoded.groups, rewrite)
+    if type(decoded.groups) == "table" then
+      local groups = {}
+      for key, group in pairs(decoded.groups) do
+        groups[type(key) == "string" and rewrite(key) or key] = group
+      end
+      decoded.groups = groups
+    end
+    return decoded
+  end,
+}
+
+---Detect the format version of a decoded file
+---@param decoded table Decoded file
+---@return integer version
+local function detect_version(decoded)
+  if type(decoded.version) == "number" then
+    return decoded.version
+  end
+  -- Files written before the version was recorded
+  return type(decoded.groups) == "table" and 1 or 0
+end
+
+-- Backups of corrupt storage files made this session, by storage file path
+---@type table<string, string>
+local corrupt_backups = {}
+
+---Keep a copy of a storage file that could not be read, once per session
+---@param path string Storage file path
+---@param backup_dir? string Directory for the copy (default next to the file)
+---@return string backup_path
+local function backup_corrupt_file(path, backup_dir)
+  if corrupt_backups[path] then
+    return corrupt_backups[path]
+  end
+
+  local backup_path = string.format("%s.corrupt-%s", path, os.date("%Y%m%d%H%M%S"))
+  if backup_dir then
+    vim.fn.mkdir(backup_dir, "p")
+    backup_path = backup_dir .. "/" .. vim.fn.fnamemodify(backup_path, ":t")
+  end
+  uv.fs_copyfile(path, backup_path)
+  corrupt_backups[path] = backup_path
+  vim.notify(
+    string.format(
+      "Context storage %s is corrupt; a copy was saved to %s. It is left alone until :ContextGroupStorageRepair",
+      path,
+      backup_path
+    ),
+    vim.log.levels.ERROR
+  )
+  return backup_path
+end
+
+---Upgrade a decoded file or bundle to the current format
+---@param decoded table Decoded file
+---@return table decoded Upgraded file
+---@return integer version Format version before the upgrade
+function M.upgrade(decoded)
+  local version = detect_version(decoded)
+  for next_version = version + 1, SCHEMA_VERSION do
+    decoded = migrations[next_version](decoded, version)
+  end
+  return decoded, version
+end
+
+---Read and decode a storage file, upgrading older formats
+---@param path string Storage file path
+---@return {data: table, meta: table, version: integer, legacy: boolean}|nil file Nil when missing or invalid
+---@return "missing"|"corrupt"|nil err
+local function read_storage_file(path)
+  local content = utils.read_file_content(path)
+  if not content then
+    return nil, "missing"
+  end
+
+  local ok, decoded = pcall(vim.fn.json_decode, content)
+  if not ok or type(decoded) ~= "table" then
+    return nil, "corrupt"
+  end
+
+  local version
+  decoded, version = M.upgrade(decoded)
+  if type(decoded.groups) ~= "table" then
+    return nil, "corrupt"
+  end
+
+  local data = decoded.groups
+  decoded.groups = nil
+  decoded.version = nil
+
+  -- Version 0 files stored absolute paths and need a root-aware conversion
+  return { data = data, meta = decoded, version = version, legacy = version == 0 }
+end
+
+---Three-way merge of a value changed both locally and by another instance
+---Lists are merged item by item, anything else is taken from the local side
+---@param base any Value when last synced with the file
+---@param ours any Local value
+---@param theirs any Value currently in the file
+---@return any merged
+local function merge_value(base, ours, theirs)
+  if ours == nil or theirs == nil or vim.deep_equal(base, theirs) then
+    return ours
+  end
+
+  base = base or {}
+  if type(ours) ~= "table" or type(theirs) ~= "table" or type(base) ~= "table" then
+    return ours
+  end
+
+  local function item_id(item)
+    return type(item) == "table" and vim.fn.json_encode(item) or tostring(item)
+  end
+  local function id_set(list)
+    local set = {}
+    for _, item in ipairs(list) do
+      set[item_id(item)] = true
+    end
+    return set
+  end
+
+  local base_set, ours_set, theirs_set = id_set(base), id_set(ours), id_set(theirs)
+
+  -- Drop items the other instance removed, keep items added on either side
+  local merged = vim.tbl_filter(function(item)
+    local id = item_id(item)
+    return theirs_set[id] or not base_set[id]
+  end, ours)
+  for _, item in ipairs(theirs) do
+    local id = item_id(item)
+    if not base_set[id] and not ours_set[id] then
+      table.insert(merged, item)
+    end
+  end
+
+  return merged
+end
+
 -- Private Storage implementation
 ---@class Storage
 ---@field path string Storage file path
 ---@field data table Data cache
+---@field meta table File metadata stored next to the data (e.g. project root)
+---@field legacy boolean Loaded from a file written before metadata was stored
+---@field pretty boolean Write indented JSON with sorted keys
+---@field base table Data as last read from or written to the file
+---@field dirty table<string, boolean> Keys changed since the last sync
+---@field overwrite boolean Replace the file instead of merging on next save
+---@field readonly boolean File was written by a newer version of the plugin, or is corrupt
+---@field corrupt boolean File could not be decoded; it is not written until repaired
+---@field backup_dir? string Directory for the copy of a corrupt file, next to the file when unset
+---@field fallback? Storage Storage whose groups apply where this one has none, e.g. the default branch's groups
+---@field watcher? uv_fs_event_t File watcher
 local Storage = {}
 Storage.__index = Storage
 
----Create new storage instance
----@param component string Component identifier
+---Create storage instance backed by an explicit file
+---@param path string Storage file path
+---@param opts? {pretty: boolean, backup_dir: string} Options {pretty: Write human-readable JSON, backup_dir: Directory for the copy of a corrupt file}
 ---@return Storage
-function Storage.new(component)
+function Storage.open(path, opts)
   local self = setmetatable({}, Storage)
-  self.path = config.get_storage_path(component)
+  self.path = path
+  self.backup_dir = opts and opts.backup_dir
   self.data = {}
+  self.meta = {}
+  self.legacy = false
+  self.pretty = opts and opts.pretty or false
+  self.base = {}
+  self.dirty = {}
+  self.overwrite = false
+  self.readonly = false
+  self.corrupt = false
   self:load()
   return self
 end
 
+---Create new storage instance
+---@param component string Component identifier
+---@return Storage
+function Storage.new(component)
+  return Storage.open(config.get_storage_path(component))
+end
+
 ---Load data from storage file
 ---@return boolean success
 function Storage:load()
-  local content = utils.read_file_content(self.path)
-  if not content then
+  local file, err = read_storage_file(self.path)
+  if not file then
+    if err == "corrupt" then
+      -- Saving would replace the unreadable groups with whatever is changed in memory
+      backup_corrupt_file(self.path, self.backup_dir)
+      self.corrupt = true
+      self.readonly = true
+    end
     return false
   end
 
-  local ok, data = pcall(vim.fn.json_decode, content)
-  if ok and data then
-    self.data = data
-    return true
+  if file.version > SCHEMA_VERSION then
+    self.readonly = true
+    vim.notify(
+      string.format("Context storage %s was written by a newer version; it will not be modified", self.path),
+      vim.log.levels.WARN
+    )
   end
 
-  return false
+  self.data = file.data
+  self.meta = file.meta
+  self.legacy = file.legacy
+  self.base = vim.deepcopy(file.data)
+  self.dirty = {}
+  return true
+end
+
+---Reload data changed by another instance, keeping unsaved local changes
+---@return boolean changed
+function Storage:reload()
+  local file = read_storage_file(self.path)
+  if not file or file.legacy then
+    return false
+  end
+
+  -- A corrupt file that was fixed elsewhere is taken as it is
+  if self.corrupt then
+    self.corrupt = false
+    self.readonly = false
+    self.data = {}
+    return self:load()
+  end
+
+  local base = vim.deepcopy(file.data)
+  for key in pairs(self.dirty) do
+    file.data[key] = self.data[key]
+    base[key] = self.base[key]
+  end
+
+  local changed = not vim.deep_equal(file.data, self.data)
+  self.data = file.data
+  self.base = base
+  self.meta = vim.tbl_extend("force", file.meta, self.meta)
+
+  local cfg = config.get()
+  if changed and cfg.on_context_change then
+    cfg.on_context_change()
+  end
+
+  return changed
+end
+
+---Acquire the advisory lock guarding the storage file
+---@return boolean locked
+function Storage:lock()
+  local lock_path = self.path .. ".lock"
+  local start = uv.hrtime()
+
+  while true do
+    local fd = uv.fs_open(lock_path, "wx", 420)
+    if fd then
+      uv.fs_write(fd, tostring(vim.fn.getpid()))
+      uv.fs_close(fd)
+      return true
+    end
+
+    local stat = uv.fs_stat(lock_path)
+    if stat and (os.time() - stat.mtime.sec) * 1000 > LOCK_STALE_MS then
+      os.remove(lock_path)
+    elseif (uv.hrtime() - start) / 1e6 > LOCK_TIMEOUT_MS then
+      return false
+    else
+      uv.sleep(10)
+    end
+  end
+end
+
+---Release the advisory lock
+function Storage:unlock()
+  os.remove(self.path .. ".lock")
 end
 
 ---Save data to storage file
+---Changes made by other instances since the last sync are merged in before writing
 ---@return boolean success
 function Storage:save()
-  local ok, encoded = pcall(vim.fn.json_encode, self.data)
+  if self.readonly then
+    return false
+  end
+
+  -- Project files live in a subdirectory that may not exist yet
+  vim.fn.mkdir(vim.fn.fnamemodify(self.path, ":h"), "p")
+
+  if not self:lock() then
+    vim.notify("Context storage is locked by another instance: " .. self.path, vim.log.levels.WARN)
+    return false
+  end
+
+  local data = self.data
+  local meta = self.meta
+  local file, err = read_storage_file(self.path)
+  if err == "corrupt" and not self.overwrite then
+    -- Another instance left the file unreadable since it was loaded
+    backup_corrupt_file(self.path, self.backup_dir)
+    self.corrupt = true
+    self.readonly = true
+    self:unlock()
+    return false
+  end
+  if file and not file.legacy and not self.overwrite then
+    data = file.data
+    for key in pairs(self.dirty) do
+      data[key] = merge_value(self.base[key], self.data[key], file.data[key])
+    end
+    meta = vim.tbl_extend("force", file.meta, self.meta)
+  end
+
+  local encode = self.pretty and utils.json_encode_pretty or vim.fn.json_encode
+  local ok, encoded = pcall(encode, vim.tbl_extend("force", meta, { version = SCHEMA_VERSION, groups = data }))
   if not ok then
+    self:unlock()
     return false
   end
 
-  return utils.write_file_content(self.path, encoded)
+  local success = utils.write_file_atomic(self.path, encoded)
+  self:unlock()
+
+  if success then
+    self.data = data
+    self.meta = meta
+    self.base = vim.deepcopy(data)
+    self.dirty = {}
+    self.overwrite = false
+  end
+
+  return success
 end
 
 ---Get value for key
 ---@param key string
 ---@return any value
 function Storage:get(key)
-  return self.data[key]
+  local value = self.data[key]
+  if value == nil and self.fallback then
+    return self.fallback:get(key)
+  end
+  -- False marks a key deleted here while the fallback still has it
+  return value or nil
+end
+
+---Get all groups, including those only the fallback has
+---@return table<string, any> data Must not be modified
+function Storage:all()
+  if not self.fallback then
+    return self.data
+  end
+
+  local all = vim.tbl_extend("force", {}, self.fallback:all())
+  for key, value in pairs(self.data) do
+    all[key] = value or nil
+  end
+  return all
 end
 
 ---Set value for key
@@ -65,6 +434,7 @@ end
 ---@return boolean success
 function Storage:set(key, value)
   self.data[key] = value
+  self.dirty[key] = true
   return self:save()
 end
 
@@ -72,31 +442,518 @@ end
 ---@param key string
 ---@return boolean success
 function Storage:delete(key)
-  self.data[key] = nil
+  if self.fallback and self.fallback:get(key) ~= nil then
+    self.data[key] = false
+  else
+    self.data[key] = nil
+  end
+  self.dirty[key] = true
   return self:save()
 end
 
 ---Clear all data
 ---@return boolean success
 function Storage:clear()
+  for key in pairs(self.data) do
+    self.dirty[key] = true
+  end
   self.data = {}
   return self:save()
 end
 
+---Replace all data, overwriting the file instead of merging
+---@param data table New data
+---@return boolean success
+function Storage:replace(data)
+  self.data = data
+  self.legacy = false
+  self.overwrite = true
+  return self:save()
+end
+
+---Watch the storage file and reload when another instance changes it
+function Storage:watch()
+  if self.watcher then
+    return
+  end
+
+  -- Watch the directory: atomic writes replace the file and its inode
+  local dir = vim.fn.fnamemodify(self.path, ":h")
+  local name = vim.fn.fnamemodify(self.path, ":t")
+  vim.fn.mkdir(dir, "p")
+
+  local watcher = uv.new_fs_event()
+  if not watcher then
+    return
+  end
+
+  local reload_pending = false
+  local ok = watcher:start(dir, {}, function(err, filename)
+    if err or filename ~= name or reload_pending then
+      return
+    end
+    reload_pending = true
+    vim.schedule(function()
+      reload_pending = false
+      self:reload()
+    end)
+  end)
+
+  if ok then
+    self.watcher = watcher
+  else
+    watcher:close()
+  end
+end
+
+---Stop watching the storage file
+function Storage:unwatch()
+  if self.watcher then
+    self.watcher:stop()
+    self.watcher:close()
+    self.watcher = nil
+  end
+end
+
+-- Directory (relative to storage_path) holding per-project storage files
+local PROJECTS_DIR = "projects"
+
+-- Directory (relative to storage_path) holding copies of corrupt shared files, per project
+local BACKUPS_DIR = "backups"
+
+---Encode a project root into a file name component
+---@param root string Project root directory
+---@return string name
+local function encode_root(root)
+  return (root:gsub("[^%w%._%-]", function(c)
+    return string.format("%%%02X", c:byte())
+  end))
+end
+
+---Decode a file name component back into a project root
+---@param name string Encoded name
+---@return string root
+local function decode_root(name)
+  return (name:gsub("%%(%x%x)", function(hex)
+    return string.char(tonumber(hex, 16))
+  end))
+end
+
 -- Storage instance cache
 ---@type table<string, Storage>
 local storage_cache = {}
 
----Get storage instance for a project
+-- Branch storage instance cache, keyed by root and branch
+---@type table<string, Storage>
+local branch_cache = {}
+
+-- Shared (repo-committed) storage instance cache
+---@type table<string, Storage>
+local shared_cache = {}
+
+---Drop the personal storages and git state of a project from the caches, closing their watchers
+---@param root string Canonical project root
+local function evict_project(root)
+  branches.close(root)
+  if storage_cache[root] then
+    storage_cache[root]:unwatch()
+    storage_cache[root] = nil
+  end
+  for cache_key, store in pairs(branch_cache) do
+    if vim.startswith(cache_key, root .. "\n") then
+      store:unwatch()
+      branch_cache[cache_key] = nil
+    end
+  end
+end
+
+-- Whether the legacy global context.json has been checked this session
+local legacy_checked = false
+
+---Get storage component identifier for a project
 ---@param root string Project root directory
+---@return string component
+local function project_component(root)
+  return PROJECTS_DIR .. "/" .. encode_root(root)
+end
+
+---Get storage component identifier for a branch of a project
+---@param root string Canonical project root
+---@param branch string Branch name
+---@return string component
+local function branch_component(root, branch)
+  return PROJECTS_DIR .. "/branches/" .. encode_root(root) .. "/" .. encode_root(branch)
+end
+
+---Convert a stored absolute path to its root-relative form
+---@param path string Stored path
+---@param root string Canonical project root
+---@return string path Relative path, or the canonical absolute path when outside the project
+local function relativize_path(path, root)
+  if not vim.startswith(path, "/") then
+    return path
+  end
+
+  path = utils.canonical_path(path)
+  if path == root then
+    return "."
+  end
+  if vim.startswith(path, root .. "/") then
+    return path:sub(#root + 2)
+  end
+  return path
+end
+
+---Rewrite absolute keys and entries as root-relative paths
+---@param groups table Stored groups
+---@param root string Canonical project root
+---@return table data, boolean changed
+local function relativize_groups(groups, root)
+  local changed = false
+  local data = {}
+
+  for key, group in pairs(groups) do
+    local new_key = relativize_path(key, root)
+    changed = changed or new_key ~= key
+
+    -- Merge groups whose keys collapse to the same relative path
+    local merged = data[new_key] or {}
+    local seen = {}
+    for _, file in ipairs(merged) do
+      seen[file] = true
+    end
+    for _, file in ipairs(type(group) == "table" and group or {}) do
+      local new_file = relativize_path(file, root)
+      changed = changed or new_file ~= file
+      if not seen[new_file] then
+        seen[new_file] = true
+        table.insert(merged, new_file)
+      end
+    end
+    data[new_key] = merged
+  end
+
+  return data, changed
+end
+
+---Find storage left behind by the same project at another root
+---A project is matched by its git root commit; outside git only a moved root with the same name matches
+---Files are only decoded: other projects' files are never upgraded, backed up or watched from here
+---@param root string Canonical project root
+---@return {path: string, data: table, meta: table}|nil previous, boolean moved Whether the old root no longer exists
+local function find_previous_location(root)
+  local dir = config.get().storage_path .. "/" .. PROJECTS_DIR
+  local project_id = project.get_project_id(root)
+  local name = vim.fn.fnamemodify(root, ":t")
+  local best, best_time, best_moved = nil, -1, false
+
+  for _, path in ipairs(vim.fn.glob(dir .. "/*.json", false, true)) do
+    local candidate = read_storage_file(path)
+    local old_root = candidate and candidate.meta.root
+    if old_root and old_root ~= root and not vim.tbl_isempty(candidate.data) then
+      local moved = vim.fn.isdirectory(old_root) == 0
+      local matches
+      if project_id then
+        matches = candidate.meta.project_id == project_id
+      else
+        matches = moved and not candidate.meta.project_id and vim.fn.fnamemodify(old_root, ":t") == name
+      end
+
+      local mtime = vim.fn.getftime(path)
+      if matches and mtime > best_time then
+        best, best_time, best_moved = { path = path, data = candidate.data, meta = candidate.meta }, mtime, moved
+      end
+    end
+  end
+
+  return best, best_moved
+end
+
+---Split the legacy global context.json into per-project storage files
+---@return number migrated Number of groups migrated
+function M.migrate_legacy()
+  legacy_checked = true
+
+  local legacy_path = config.get_storage_path("context")
+  local content = utils.read_file_content(legacy_path)
+  if not content then
+    return 0
+  end
+
+  local ok, data = pcall(vim.fn.json_decode, content)
+  if not ok or type(data) ~= "table" then
+    vim.notify("Cannot migrate legacy context storage: invalid JSON in " .. legacy_path, vim.log.levels.WARN)
+    return 0
+  end
+
+  -- Group keys by project root, keeping keys whose path no longer exists
+  local by_root = {}
+  local unresolved = 0
+  for key, group in pairs(data) do
+    if vim.fn.filereadable(key) == 1 or vim.fn.isdirectory(key) == 1 then
+      local root = utils.canonical_path(project.find_root(key))
+      by_root[root] = by_root[root] or {}
+      by_root[root][key] = group
+    else
+      unresolved = unresolved + 1
+    end
+  end
+
+  local migrated = 0
+  for root, groups in pairs(by_root) do
+    local store = M.get_branch_storage(root, nil)
+    local relative = relativize_groups(groups, root)
+    migrated = migrated + vim.tbl_count(relative)
+    -- Never overwrite groups already present in the project file
+    store:replace(vim.tbl_extend("force", relative, store.data))
+  end
+
+  -- Keep the original file around as a backup
+  local backup_path = legacy_path .. ".migrated"
+  vim.fn.rename(legacy_path, backup_path)
+
+  if unresolved > 0 then
+    vim.notify(
+      string.format(
+        "Migrated %d context groups to per-project storage; %d groups for missing files were kept in %s",
+        migrated,
+        unresolved,
+        backup_path
+      ),
+      vim.log.levels.WARN
+    )
+  end
+
+  return migrated
+end
+
+---Get the personal storage of a project's default branch
+---@param root string Canonical project root
 ---@return Storage
-function M.get_storage(root)
+local function get_project_storage(root)
   if not storage_cache[root] then
-    storage_cache[root] = Storage.new("context")
+    local store = Storage.new(project_component(root))
+    local exists = vim.fn.filereadable(store.path) == 1
+
+    -- One-time conversion of files that stored absolute paths
+    if store.legacy then
+      store.meta.root = root
+      store:replace((relativize_groups(store.data, root)))
+    end
+
+    -- Pick up groups stored before the project was moved or cloned elsewhere
+    if not exists then
+      local previous, moved = find_previous_location(root)
+      if previous then
+        store.meta.root = root
+        store.meta.project_id = previous.meta.project_id
+        store:replace(vim.deepcopy(previous.data))
+        if moved then
+          os.remove(previous.path)
+          evict_project(previous.meta.root)
+        end
+        vim.notify(string.format("Restored context groups from %s", previous.meta.root), vim.log.levels.INFO)
+      end
+    end
+
+    store.meta.root = root
+    if store.meta.project_id == nil then
+      store.meta.project_id = project.get_project_id(root)
+    end
+    store:watch()
+    storage_cache[root] = store
+  end
+
+  if not legacy_checked then
+    M.migrate_legacy()
   end
+
   return storage_cache[root]
 end
 
+---Get the personal storage of a branch of a project
+---Groups missing on the branch fall back to the default branch's groups
+---@param root string Project root directory
+---@param branch string|nil Branch name, nil for the default branch
+---@return Storage
+function M.get_branch_storage(root, branch)
+  root = utils.canonical_path(root)
+  local store = get_project_storage(root)
+  if not branch or branch == branches.get_default_branch(root) then
+    return store
+  end
+
+  local cache_key = root .. "\n" .. branch
+  if not branch_cache[cache_key] then
+    local branch_store = Storage.new(branch_component(root, branch))
+    branch_store.fallback = store
+    branch_store.meta.root = root
+    branch_store.meta.branch = branch
+    branch_store:watch()
+    branch_cache[cache_key] = branch_store
+  end
+  return branch_cache[cache_key]
+end
+
+---Get storage instance for a project
+---With `branch_groups` enabled this is the storage of the checked out branch
+---@param root string Project root directory
+---@return Storage
+function M.get_storage(root)
+  root = utils.canonical_path(root)
+  return M.get_branch_storage(root, branches.get_branch(root))
+end
+
+---Get the repo-committed shared storage for a project
+---@param root string Project root directory
+---@return Storage|nil store Nil when shared groups are disabled
+function M.get_shared_storage(root)
+  local shared_file = config.get().shared_file
+  if not shared_file or shared_file == "" then
+    return nil
+  end
+
+  root = utils.canonical_path(root)
+  if not shared_cache[root] then
+    -- Copies of a corrupt shared file are kept out of the working tree
+    local project_key = encode_root(project.get_project_id(root) or root)
+    local backup_dir = config.get().storage_path .. "/" .. BACKUPS_DIR .. "/" .. project_key
+    shared_cache[root] = Storage.open(root .. "/" .. shared_file, { pretty = true, backup_dir = backup_dir })
+    shared_cache[root]:watch()
+  end
+  return shared_cache[root]
+end
+
+---Stop watching every storage file, e.g. before Neovim exits
+function M.close()
+  for _, cache in ipairs({ storage_cache, branch_cache, shared_cache }) do
+    for _, store in pairs(cache) do
+      store:unwatch()
+    end
+  end
+end
+
+---Open the stored groups of a project without setting up its storage
+---Unlike get_storage this starts no watcher, runs no git commands and never looks for the groups of a moved
+---project, for passes over every stored project
+---@param root string Canonical project root
+---@param scope "personal"|"shared" Personal storage of the default branch, or the shared file
+---@return Storage|nil store Nil when shared groups are disabled
+function M.peek_storage(root, scope)
+  if scope == "shared" then
+    local shared_file = config.get().shared_file
+    if not shared_file or shared_file == "" then
+      return nil
+    end
+    return shared_cache[root] or Storage.open(root .. "/" .. shared_file, { pretty = true })
+  end
+  return storage_cache[root] or Storage.new(project_component(root))
+end
+
+---Get the identity of a well-formed stored group entry
+---Entries are strings, or tables whose `entry` string carries metadata such as a note
+---@param entry any Stored entry
+---@return string|nil id Nil when the entry is malformed
+local function get_entry_id(entry)
+  if type(entry) == "table" and not utils.is_list(entry) then
+    entry = entry.entry
+  end
+  return type(entry) == "string" and entry ~= "" and entry or nil
+end
+
+---Remove malformed groups and entries from a store
+---A corrupt file starts over empty; its content is kept in the backup made when it was read
+---@param store Storage
+---@return string[] issues Description of every found problem
+---@return boolean success Whether the fixes were saved
+local function repair_store(store)
+  local issues = {}
+  local data = {}
+
+  if store.corrupt then
+    store.corrupt = false
+    store.readonly = false
+    store.data = {}
+    local backup_path = backup_corrupt_file(store.path, store.backup_dir)
+    table.insert(issues, "unreadable file replaced by an empty one, see " .. backup_path)
+    return issues, store:replace({})
+  end
+
+  for key, group in pairs(store.data) do
+    if group == false and store.fallback then
+      -- Deleted on a branch while the default branch still has it
+      data[key] = group
+    elseif type(group) ~= "table" or (next(group) ~= nil and not utils.is_list(group)) then
+      table.insert(issues, string.format("%s: group is not a list, removed", key))
+    else
+      local entries = {}
+      local seen = {}
+      for _, entry in ipairs(group) do
+        local id = get_entry_id(entry)
+        if not id then
+          table.insert(issues, string.format("%s: invalid entry %s removed", key, vim.inspect(entry)))
+        elseif seen[id] then
+          table.insert(issues, string.format("%s: duplicate entry %s removed", key, vim.inspect(entry)))
+        else
+          seen[id] = true
+          table.insert(entries, entry)
+        end
+      end
+
+      -- Named groups may legitimately be empty
+      if #entries == 0 and not vim.startswith(key, M.NAMED_GROUP_PREFIX) then
+        table.insert(issues, string.format("%s: empty group removed", key))
+      else
+        data[key] = entries
+      end
+    end
+  end
+
+  if #issues > 0 then
+    return issues, store:replace(data)
+  end
+
+  return issues, true
+end
+
+---Report and fix broken groups in a project's personal and shared storage
+---@param root string Project root directory
+---@return {path: string, issues: string[], success: boolean}[] report One item per storage file
+function M.repair(root)
+  local report = {}
+  for _, store in ipairs({ M.get_storage(root), M.get_shared_storage(root) }) do
+    local issues, success = repair_store(store)
+    table.insert(report, { path = store.path, issues = issues, success = success })
+  end
+  return report
+end
+
+---List every project that has stored context groups
+---@return {root: string, path: string, groups: number}[] projects Sorted by root
+function M.list_projects()
+  local dir = config.get().storage_path .. "/" .. PROJECTS_DIR
+  local projects = {}
+
+  for _, path in ipairs(vim.fn.glob(dir .. "/*.json", false, true)) do
+    local root = decode_root(vim.fn.fnamemodify(path, ":t:r"))
+    local store = M.peek_storage(root, "personal")
+    local count = vim.tbl_count(store.data)
+    if count > 0 then
+      table.insert(projects, {
+        root = root,
+        path = path,
+        groups = count,
+      })
+    end
+  end
+
+  table.sort(projects, function(a, b)
+    return a.root < b.root
+  end)
+
+  return projects
+end
+
 -- Export the Storage class for direct use if needed
 M.Storage = Storage
 
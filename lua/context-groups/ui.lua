@@ -3,18 +3,80 @@
 
 local config = require("context-groups.config")
 local core = require("context-groups.core")
+local groups = require("context-groups.groups")
 local picker = require("context-groups.picker")
 
 local M = {}
 
+-- How long the current buffer has to stay entered before its group is checked for renamed files
+local RENAME_REPAIR_DELAY_MS = 300
+
+-- Complete named group names of the current project
+---@param arg_lead string Text being completed
+---@return string[] names
+local function complete_group_names(arg_lead)
+  local root = core.get_current_root()
+  if not root then
+    return {}
+  end
+
+  local names = vim.tbl_map(function(group)
+    return group.name
+  end, groups.list(root))
+
+  return vim.tbl_filter(function(name)
+    return vim.startswith(name, arg_lead)
+  end, names)
+end
+
+---Complete local branch names of the current project
+---@param arg_lead string Text typed so far
+---@return string[] branches
+local function complete_branches(arg_lead)
+  local root = core.get_current_root()
+  return vim.tbl_filter(function(branch)
+    return vim.startswith(branch, arg_lead)
+  end, root and require("context-groups.branches").list(root) or {})
+end
+
+---Complete a bundle file, then a conflict policy
+---@param arg_lead string Text typed so far
+---@param cmd_line string Whole command line
+---@return string[] candidates
+local function complete_bundle_load(arg_lead, cmd_line)
+  if #vim.split(vim.trim(cmd_line), "%s+") - (arg_lead == "" and 0 or 1) >= 2 then
+    return vim.tbl_filter(function(policy)
+      return vim.startswith(policy, arg_lead)
+    end, { "merge", "replace", "skip" })
+  end
+  return vim.fn.getcompletion(arg_lead, "file")
+end
+
 -- Command groups organized by functionality
 local commands = {
   -- Context group commands
   context = {
     -- Add file to context group
     add = function(args)
-      if args.args ~= "" then
-        local success = core.add_context_file(args.args)
+      if args.args == "" and args.range > 0 then
+        -- A line range adds that slice of the current file
+        local file = vim.api.nvim_buf_get_name(0)
+        local spec = string.format("%s:%d-%d", file, args.line1, args.line2)
+        if core.add_context_file(spec, nil, { scope = args.bang and "shared" or nil }) then
+          vim.notify(string.format("Added %s to context group", vim.fn.fnamemodify(spec, ":~:.")))
+        end
+      elseif args.args ~= "" then
+        local opts = { scope = args.bang and "shared" or nil }
+        local success
+        if vim.fn.isdirectory(args.args) == 1 then
+          -- Directories are stored as live rules
+          success = core.add_context_rule(vim.fn.fnamemodify(args.args, ":p"), nil, opts)
+        elseif vim.fn.filereadable(args.args) ~= 1 and core.is_rule_pattern(args.args) then
+          -- Existing files such as `pages/[id].tsx` are never taken for globs
+          success = core.add_context_rule(args.args, nil, opts)
+        else
+          success = core.add_context_file(args.args, nil, opts)
+        end
         if success then
           vim.notify(string.format("Added %s to context group", vim.fn.fnamemodify(args.args, ":~:.")))
         else
@@ -35,6 +97,20 @@ local commands = {
       picker.show_imports_picker()
     end,
 
+    -- Preview and add the files the current file depends on, up to an optional depth
+    add_dependencies = function(args)
+      local depth = tonumber(args.args)
+      if args.args ~= "" and not depth then
+        vim.notify("Usage: ContextGroupAddDeps[!] [depth]", vim.log.levels.ERROR)
+        return
+      end
+      if args.bang then
+        core.add_dependencies(nil, { depth = depth })
+      else
+        picker.show_dependencies_picker({ depth = depth })
+      end
+    end,
+
     -- Remove file from context group
     remove = function(args)
       if args.args ~= "" then
@@ -54,10 +130,177 @@ local commands = {
       local success = core.clear_context_group()
       if success then
         vim.notify("Cleared context group")
-      else
+      elseif success == false then
         vim.notify("Failed to clear context group", vim.log.levels.ERROR)
       end
     end,
+
+    -- Undo the last context group edit
+    undo = function()
+      core.undo()
+    end,
+
+    -- Redo the last undone context group edit
+    redo = function()
+      core.redo()
+    end,
+
+    -- Browse past states of the context groups
+    history = function()
+      picker.show_history()
+    end,
+
+    -- Repair entries of files that were renamed or moved
+    fix_renames = function()
+      core.fix_renames()
+    end,
+
+    -- Save the current group, or with ! every group, to a bundle file
+    save = function(args)
+      core.save_bundle(args.args, nil, { all = args.bang })
+    end,
+
+    -- Load a bundle file, into the shared file with !
+    load = function(args)
+      core.load_bundle(args.fargs[1], nil, {
+        policy = args.fargs[2],
+        scope = args.bang and "shared" or nil,
+      })
+    end,
+
+    -- Copy the current group, or with ! every group, from another branch
+    copy_branch = function(args)
+      core.copy_from_branch(args.args, nil, { all = args.bang })
+    end,
+  },
+
+  -- Named group commands
+  named = {
+    -- Create a named group and activate it
+    create = function(args)
+      local root = core.get_current_root()
+      if root and groups.create(root, args.args, { scope = args.bang and "shared" or nil }) then
+        groups.activate(root, args.args)
+        vim.notify("Created and activated context group " .. args.args)
+      end
+    end,
+
+    -- Activate a named group, or pick one
+    activate = function(args)
+      if args.args == "" then
+        picker.show_named_groups()
+        return
+      end
+      local root = core.get_current_root()
+      if root and groups.activate(root, args.args) then
+        vim.notify("Activated context group " .. args.args)
+      end
+    end,
+
+    -- Deactivate the active named group
+    deactivate = function()
+      local root = core.get_current_root()
+      if root then
+        groups.deactivate(root)
+        vim.notify("Buffers use their own context groups again")
+      end
+    end,
+
+    -- Rename a named group
+    rename = function(args)
+      if #args.fargs ~= 2 then
+        vim.notify("Usage: ContextGroupRename {old} {new}", vim.log.levels.ERROR)
+        return
+      end
+      local root = core.get_current_root()
+      local old_name, new_name = args.fargs[1], args.fargs[2]
+      if root and groups.rename(root, old_name, new_name) then
+        vim.notify(string.format("Renamed context group %s to %s", old_name, new_name))
+      end
+    end,
+
+    -- Delete a named group
+    delete = function(args)
+      local root = core.get_current_root()
+      if root and groups.delete(root, args.args) then
+        vim.notify("Deleted context group " .. args.args)
+      end
+    end,
+
+    -- List named groups
+    list = function()
+      picker.show_named_groups()
+    end,
+  },
+
+  -- Storage maintenance commands
+  storage = {
+    -- Report and fix broken groups in project storage
+    repair = function()
+      local root = core.get_current_root()
+      if not root then
+        vim.notify("No valid file path found", vim.log.levels.ERROR)
+        return
+      end
+
+      local lines = {}
+      local failed = {}
+      for _, item in ipairs(require("context-groups.storage").repair(root)) do
+        for _, issue in ipairs(item.issues) do
+          table.insert(lines, string.format("%s: %s", vim.fn.fnamemodify(item.path, ":~"), issue))
+        end
+        if not item.success then
+          table.insert(failed, vim.fn.fnamemodify(item.path, ":~"))
+        end
+      end
+
+      if #failed > 0 then
+        vim.notify(
+          string.format("Could not save repairs to %s:\n%s", table.concat(failed, ", "), table.concat(lines, "\n")),
+          vim.log.levels.ERROR
+        )
+      elseif #lines == 0 then
+        vim.notify("Context storage is healthy")
+      else
+        vim.notify(
+          string.format("Repaired %d storage issues:\n%s", #lines, table.concat(lines, "\n")),
+          vim.log.levels.WARN
+        )
+      end
+    end,
+
+    -- Prune keys and entries of files that no longer exist
+    gc = function(args)
+      local root = core.get_current_root()
+      if not root then
+        vim.notify("No valid file path found", vim.log.levels.ERROR)
+        return
+      end
+
+      -- Anything but the exact flag is a typo that must not turn into a real prune
+      if args.args ~= "" and args.args ~= "dry-run" then
+        vim.notify("Usage: ContextGroupGC [dry-run]", vim.log.levels.ERROR)
+        return
+      end
+
+      local dry_run = args.args == "dry-run"
+      local lines = {}
+      for _, item in ipairs(core.collect_garbage(root, { dry_run = dry_run })) do
+        for _, key in ipairs(item.keys) do
+          table.insert(lines, string.format("[%s] %s (group)", item.scope, key))
+        end
+        for _, entry in ipairs(item.entries) do
+          table.insert(lines, string.format("[%s] %s: %s", item.scope, entry.key, entry.value))
+        end
+      end
+
+      if #lines == 0 then
+        vim.notify("No stale context groups or entries")
+      else
+        local action = dry_run and "Would prune" or "Pruned"
+        vim.notify(string.format("%s %d stale keys and entries:\n%s", action, #lines, table.concat(lines, "\n")))
+      end
+    end,
   },
 
   -- Preference toggle commands
@@ -108,9 +351,9 @@ local commands = {
       require("context-groups").call_code2prompt_current()
     end,
 
-    -- Copy contents of all open buffers to clipboard in a formatted way
-    generate = function()
-      require("context-groups").call_code2prompt()
+    -- Copy contents of all open buffers (or a named group) to clipboard in a formatted way
+    generate = function(args)
+      require("context-groups").call_code2prompt({ group = args.args ~= "" and args.args or nil })
     end,
   },
 
@@ -171,7 +414,9 @@ function M.register_commands()
   create_command("ContextGroupAdd", commands.context.add, {
     nargs = "?",
     complete = "file",
-    desc = "Add file to context group",
+    bang = true,
+    range = true,
+    desc = "Add file, directory or glob to context group (! adds to the shared group)",
   })
 
   create_command("ContextGroupShow", commands.context.show, {
@@ -182,6 +427,12 @@ function M.register_commands()
     desc = "Add imported files to context group",
   })
 
+  create_command("ContextGroupAddDeps", commands.context.add_dependencies, {
+    nargs = "?",
+    bang = true,
+    desc = "Add the project files the current file imports, up to a depth (! adds without preview)",
+  })
+
   create_command("ContextGroupRemove", commands.context.remove, {
     nargs = "?",
     complete = function()
@@ -194,6 +445,89 @@ function M.register_commands()
     desc = "Clear current context group",
   })
 
+  create_command("ContextGroupUndo", commands.context.undo, {
+    desc = "Undo the last context group edit",
+  })
+
+  create_command("ContextGroupRedo", commands.context.redo, {
+    desc = "Redo the last undone context group edit",
+  })
+
+  create_command("ContextGroupHistory", commands.context.history, {
+    desc = "Browse and restore past context group states",
+  })
+
+  create_command("ContextGroupFixRenames", commands.context.fix_renames, {
+    desc = "Rewrite context group entries of renamed or moved files",
+  })
+
+  create_command("ContextGroupSave", commands.context.save, {
+    nargs = 1,
+    bang = true,
+    complete = "file",
+    desc = "Save the context group to a JSON/YAML bundle (! saves every group)",
+  })
+
+  create_command("ContextGroupLoad", commands.context.load, {
+    nargs = "+",
+    bang = true,
+    complete = complete_bundle_load,
+    desc = "Load context groups from a bundle: {file} [merge|replace|skip] (! loads into the shared file)",
+  })
+
+  create_command("ContextGroupCopyBranch", commands.context.copy_branch, {
+    nargs = 1,
+    bang = true,
+    complete = complete_branches,
+    desc = "Copy the context group from another branch (! copies every group)",
+  })
+
+  -- Named group commands
+  create_command("ContextGroupCreate", commands.named.create, {
+    nargs = 1,
+    bang = true,
+    desc = "Create and activate a named context group (! creates it in the shared file)",
+  })
+
+  create_command("ContextGroupActivate", commands.named.activate, {
+    nargs = "?",
+    complete = complete_group_names,
+    desc = "Activate a named context group",
+  })
+
+  create_command("ContextGroupDeactivate", commands.named.deactivate, {
+    desc = "Deactivate the active named context group",
+  })
+
+  create_command("ContextGroupRename", commands.named.rename, {
+    nargs = "+",
+    complete = complete_group_names,
+    desc = "Rename a named context group",
+  })
+
+  create_command("ContextGroupDelete", commands.named.delete, {
+    nargs = 1,
+    complete = complete_group_names,
+    desc = "Delete a named context group",
+  })
+
+  create_command("ContextGroupList", commands.named.list, {
+    desc = "List named context groups",
+  })
+
+  -- Storage commands
+  create_command("ContextGroupStorageRepair", commands.storage.repair, {
+    desc = "Report and fix broken entries in context group storage",
+  })
+
+  create_command("ContextGroupGC", commands.storage.gc, {
+    nargs = "?",
+    complete = function()
+      return { "dry-run" }
+    end,
+    desc = "Prune context groups and entries of deleted files (dry-run only reports them)",
+  })
+
   -- Preference commands
   create_command("ContextGroupToggleStdlib", commands.prefs.toggle_stdlib, {
     desc = "Toggle visibility of standard library imports",
@@ -219,7 +553,9 @@ function M.register_commands()
   })
 
   create_command("ContextGroupBuffer2Prompt", commands.code2prompt.generate, {
-    desc = "Copy contents of all open buffers to clipboard in a formatted way",
+    nargs = "?",
+    complete = complete_group_names,
+    desc = "Copy contents of all open buffers (or a named group) to clipboard in a formatted way",
   })
 
   -- LSP diagnostics commands
@@ -333,13 +669,110 @@ function M.setup_keymaps()
   end
 end
 
--- Initialize UI components
+-- Set up autocommands
+function M.setup_autocmds()
+  local augroup = vim.api.nvim_create_augroup("ContextGroups", { clear = true })
+
+  vim.api.nvim_create_autocmd("BufWritePost", {
+    group = augroup,
+    callback = function(args)
+      core.sync_buffer_entries(args.buf)
+      require("context-groups.graph").refresh(args.buf)
+    end,
+  })
+
+  vim.api.nvim_create_autocmd({ "BufReadPre", "BufUnload" }, {
+    group = augroup,
+    callback = function(args)
+      require("context-groups.entries").untrack(args.buf)
+    end,
+  })
+
+  -- Range entries follow edits of the buffers being edited and are stored once the buffer is written. Buffers that
+  -- are only loaded in the background, such as by previews or :vimgrep, are left alone so their projects' storage
+  -- is not opened. Reading covers `:edit!`, which reloads the current buffer without entering it.
+  vim.api.nvim_create_autocmd({ "BufEnter", "BufReadPost" }, {
+    group = augroup,
+    callback = function(args)
+      if vim.bo[args.buf].buftype == "" and args.buf == vim.api.nvim_get_current_buf() then
+        core.track_buffer_entries(args.buf)
+      end
+    end,
+  })
+
+  -- Close the storage file and git HEAD watchers before exiting
+  vim.api.nvim_create_autocmd("VimLeavePre", {
+    group = augroup,
+    callback = function()
+      require("context-groups.storage").close()
+      require("context-groups.branches").close()
+    end,
+  })
+
+  -- Files of an entered buffer's group that disappeared were probably renamed. Each buffer is checked once per
+  -- session, after buffer switches have settled, so quickly moving through buffers does no file or git I/O
+  local checked_buffers = {}
+  local repair_timer = (vim.uv or vim.loop).new_timer()
+  vim.api.nvim_create_autocmd("BufEnter", {
+    group = augroup,
+    callback = function(args)
+      if vim.bo[args.buf].buftype ~= "" or checked_buffers[args.buf] then
+        return
+      end
+      repair_timer:stop()
+      repair_timer:start(
+        RENAME_REPAIR_DELAY_MS,
+        0,
+        vim.schedule_wrap(function()
+          local bufnr = vim.api.nvim_get_current_buf()
+          if bufnr == args.buf and not checked_buffers[bufnr] then
+            checked_buffers[bufnr] = true
+            core.repair_group_renames(bufnr)
+          end
+        end)
+      )
+    end,
+  })
+  vim.api.nvim_create_autocmd("BufWipeout", {
+    group = augroup,
+    callback = function(args)
+      checked_buffers[args.buf] = nil
+    end,
+  })
+
+  -- Renames done in file explorers rewrite the groups that refer to the old paths
+  vim.api.nvim_create_autocmd("User", {
+    group = augroup,
+    pattern = { "MiniFilesActionRename", "MiniFilesActionMove" },
+    callback = function(args)
+      if args.data and args.data.from and args.data.to then
+        core.rename_file(args.data.from, args.data.to)
+      end
+    end,
+  })
+
+  vim.api.nvim_create_autocmd("User", {
+    group = augroup,
+    pattern = "OilActionsPost",
+    callback = function(args)
+      for _, action in ipairs(args.data and args.data.actions or {}) do
+        if action.type == "move" and action.src_url and action.dest_url then
+          core.rename_file((action.src_url:gsub("^oil://", "")), (action.dest_url:gsub("^oil://", "")))
+        end
+      end
+    end,
+  })
+end
+
 function M.setup()
   -- Register commands
   M.register_commands()
 
   -- Set up keymaps
   M.setup_keymaps()
+
+  -- Set up autocommands
+  M.setup_autocmds()
 end
 
 return M
@@ -3,6 +3,9 @@
 
 local Utils = {}
 
+-- vim.tbl_islist was renamed in Neovim 0.10
+local islist = vim.islist or vim.tbl_islist
+
 -- File system utilities
 ---@param filepath string File path
 ---@return string|nil content
@@ -33,7 +36,128 @@ function Utils.write_file_content(filepath, content)
   return vim.fn.writefile(lines, filepath) == 0
 end
 
+---Check whether a table is a list
+---@param value table
+---@return boolean
+function Utils.is_list(value)
+  return islist(value)
+end
+
+---Encode a value as indented JSON with sorted keys, for files kept under version control
+---@param value any Value to encode
+---@return string json
+function Utils.json_encode_pretty(value)
+  local function encode(v, indent)
+    if type(v) ~= "table" or vim.tbl_isempty(v) then
+      return vim.fn.json_encode(v)
+    end
+
+    local inner = indent .. "  "
+    local parts = {}
+    if islist(v) then
+      for _, item in ipairs(v) do
+        table.insert(parts, inner .. encode(item, inner))
+      end
+      return "[\n" .. table.concat(parts, ",\n") .. "\n" .. indent .. "]"
+    end
+
+    local keys = vim.tbl_keys(v)
+    table.sort(keys)
+    for _, key in ipairs(keys) do
+      table.insert(parts, inner .. vim.fn.json_encode(tostring(key)) .. ": " .. encode(v[key], inner))
+    end
+    return "{\n" .. table.concat(parts, ",\n") .. "\n" .. indent .. "}"
+  end
+
+  return encode(value, "")
+end
+
+---Write file content atomically by writing a temporary file and renaming it into place
+---@param filepath string File path
+---@param content string Content to write
+---@return boolean success
+function Utils.write_file_atomic(filepath, content)
+  local tmp_path = string.format("%s.%d.tmp", filepath, vim.fn.getpid())
+  if not Utils.write_file_content(tmp_path, content) then
+    return false
+  end
+
+  if not (vim.uv or vim.loop).fs_rename(tmp_path, filepath) then
+    os.remove(tmp_path)
+    return false
+  end
+
+  return true
+end
+
 -- Path utilities
+---Get the canonical absolute form of a path, resolving symlinks when it exists
+---@param path string File path
+---@return string canonical_path Absolute path without trailing slash
+function Utils.canonical_path(path)
+  local abs_path = vim.fn.fnamemodify(path, ":p")
+  local real_path = (vim.uv or vim.loop).fs_realpath(abs_path)
+  return ((real_path or abs_path):gsub("(.)/$", "%1"))
+end
+
+---Convert a glob to an anchored very-magic vim regex
+---`**/` matches any number of directories, `*` and `?` never match `/`, `[...]` is kept as a character class
+---@param glob string Glob pattern
+---@return string regex
+function Utils.glob_to_regex(glob)
+  local parts = { "\\v^" }
+  local i = 1
+  while i <= #glob do
+    local char = glob:sub(i, i)
+    if glob:sub(i, i + 2) == "**/" then
+      table.insert(parts, "(.*/)?")
+      i = i + 3
+    elseif glob:sub(i, i + 1) == "**" then
+      table.insert(parts, ".*")
+      i = i + 2
+    elseif char == "*" then
+      table.insert(parts, "[^/]*")
+      i = i + 1
+    elseif char == "?" then
+      table.insert(parts, "[^/]")
+      i = i + 1
+    elseif char == "[" and glob:find("]", i + 1, true) then
+      local close = glob:find("]", i + 1, true)
+      table.insert(parts, (glob:sub(i, close):gsub("^%[!", "[^")))
+      i = close + 1
+    elseif char:match("[%w_/]") then
+      -- `\_` starts a character class, so underscores are never escaped
+      table.insert(parts, char)
+      i = i + 1
+    elseif char == "\\" or char == "^" or char == "[" or char == "]" then
+      table.insert(parts, "\\" .. char)
+      i = i + 1
+    else
+      -- Other punctuation is a very-magic metacharacter; a one-character collection matches it literally
+      table.insert(parts, "[" .. char .. "]")
+      i = i + 1
+    end
+  end
+  table.insert(parts, "$")
+  return table.concat(parts)
+end
+
+---Escape the glob characters of a path so it only matches itself
+---@param path string File path
+---@return string glob
+function Utils.glob_escape(path)
+  return (path:gsub("[%*%?%[]", "[%0]"))
+end
+
+---Check whether a path matches a glob
+---@param glob string Glob pattern
+---@param path string Path to test
+---@return boolean matches
+function Utils.glob_match(glob, path)
+  local ok, regex = pcall(vim.regex, Utils.glob_to_regex(glob))
+  return ok and regex:match_str(path) ~= nil
+end
+
 ---@param path string File path
 ---@return string relative_path
 function Utils.get_relative_path(path, root)
@@ -1,6 +1,14 @@
 -- lua/spec/context-groups/core_spec.lua
 local assert = require("luassert")
+local bundles = require("context-groups.bundles")
+local config = require("context-groups.config")
 local core = require("context-groups.core")
+local entries = require("context-groups.entries")
+local groups = require("context-groups.groups")
+local graph = require("context-groups.graph")
+local history = require("context-groups.history")
+local lsp = require("context-groups.lsp")
+local pairing = require("context-groups.pairing")
 local match = require("luassert.match")
 local project = require("context-groups.project")
 local storage = require("context-groups.storage")
@@ -15,6 +23,42 @@ describe("Core module", function()
     assert.is_function(core.add_context_file)
     assert.is_function(core.remove_context_file)
     assert.is_function(core.clear_context_group)
+    assert.is_function(core.collect_garbage)
+    assert.is_function(core.get_storage_stats)
+  end)
+
+  it("should drop low-priority files first when over the size budget", function()
+    local items = {
+      { content = "aaaa", priority = 1 },
+      { content = "bbbb" },
+      { content = "cccc" },
+    }
+    local kept, dropped = core.apply_size_budget(items, 8)
+    assert.are.same({ items[1], items[2] }, kept)
+    assert.are.same({ items[3] }, dropped)
+  end)
+
+  it("should list personal entries first and prefer them over shared ones", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    for _, file in ipairs({ "main.lua", "b.lua", "c.lua" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    root = utils.canonical_path(root)
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/main.lua"))
+
+    assert.is_true(core.add_context_file(root .. "/b.lua", nil, { scope = "shared" }))
+    assert.is_true(core.add_context_file(root .. "/c.lua", nil, { scope = "shared" }))
+    assert.is_true(core.add_context_file(root .. "/c.lua", nil, { scope = "personal" }))
+    local found = vim.tbl_map(function(entry)
+      return { path = entry.path, scope = entry.scope }
+    end, core.get_context_entries())
+    assert.are.same({
+      { path = root .. "/c.lua", scope = "personal" },
+      { path = root .. "/b.lua", scope = "shared" },
+    }, found)
+    assert.are.same({ "b.lua", "c.lua" }, storage.get_shared_storage(root):get("main.lua"))
+    vim.cmd("bwipeout!")
   end)
 
   -- Testing project root detection using function stubs
@@ -43,11 +87,108 @@ describe("Core module", function()
     getcwd_stub:revert()
   end)
 
+  it("should return no entries without a group and without warning", function()
+    vim.cmd("enew")
+    local notify_stub = stub(vim, "notify")
+    local result = core.get_context_entries()
+    notify_stub:revert()
+
+    assert.are.same({}, result)
+    assert.stub(notify_stub).was_not_called()
+    assert.is_false(core.has_context_group())
+  end)
+
   -- Testing export functionality as basic module check
   it("should have export functionality", function()
     assert.is_table(core.export)
     assert.is_function(core.export.export_contents)
   end)
+
+  it("should export the notes of the context group", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.writefile({ "" }, root .. "/main.lua")
+    vim.fn.writefile({ "return 1" }, root .. "/b.lua")
+    root = utils.canonical_path(root)
+    local cwd = vim.fn.getcwd()
+    vim.cmd.cd(vim.fn.fnameescape(root))
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/main.lua"))
+
+    assert.is_true(core.add_context_file(root .. "/b.lua"))
+    assert.is_true(core.set_entry_note("b.lua", 'the "router"'))
+    local output = table.concat(core.export.export_contents({ paths = { "b.lua" } }), "\n")
+    vim.cmd("bwipeout!")
+    vim.cmd.cd(vim.fn.fnameescape(cwd))
+    assert.is_truthy(output:find('<code path="b.lua" note="the &quot;router&quot;">', 1, true))
+  end)
+
+  it("should export files in the order of the context group", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    for _, file in ipairs({ "main.lua", "b.lua", "c.lua", "d.lua" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    root = utils.canonical_path(root)
+    local cwd = vim.fn.getcwd()
+    vim.cmd.cd(vim.fn.fnameescape(root))
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/main.lua"))
+
+    assert.is_true(core.add_context_file(root .. "/c.lua"))
+    assert.is_true(core.add_context_file(root .. "/b.lua"))
+    local output = table.concat(core.export.export_contents({ paths = { "d.lua", "b.lua", "c.lua" } }), "\n")
+    vim.cmd("bwipeout!")
+    vim.cmd.cd(vim.fn.fnameescape(cwd))
+
+    local c = output:find('<code path="c.lua">', 1, true)
+    local b = output:find('<code path="b.lua">', 1, true)
+    local d = output:find('<code path="d.lua">', 1, true)
+    assert.is_true(c < b and b < d)
+  end)
+
+  it("should export bracketed file names as files", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.mkdir(root .. "/pages", "p")
+    vim.fn.writefile({ "export default 1" }, root .. "/pages/[id].tsx")
+    vim.fn.writefile({ "" }, root .. "/pages/i.tsx")
+    root = utils.canonical_path(root)
+    local cwd = vim.fn.getcwd()
+    vim.cmd.cd(vim.fn.fnameescape(root))
+
+    local output = table.concat(core.export.export_contents({ paths = { "pages/[id].tsx" } }), "\n")
+    local globbed = table.concat(core.export.export_contents({ paths = { "rule://pages/*.tsx" } }), "\n")
+    vim.cmd.cd(vim.fn.fnameescape(cwd))
+
+    assert.is_truthy(output:find('<code path="pages/[id].tsx">', 1, true))
+    assert.is_falsy(output:find('<code path="pages/i.tsx">', 1, true))
+    assert.is_truthy(globbed:find('<code path="pages/i.tsx">', 1, true))
+  end)
+end)
+
+describe("Garbage collection", function()
+  it("should only report in a dry run and prune only what was deleted", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    for _, file in ipairs({ "a.lua", "b.lua", "c.lua" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    root = utils.canonical_path(root)
+    local store = storage.get_storage(root)
+    store:set("a.lua", { "b.lua", { entry = "c.lua", note = "gone" }, "rule://**/*.md" })
+    store:set("c.lua", { "a.lua" })
+    store:set(groups.key("empty"), {})
+    os.remove(root .. "/c.lua")
+
+    local report = core.collect_garbage(root, { dry_run = true, scopes = { "personal" } })
+    assert.are.same({ "c.lua" }, report[1].keys)
+    assert.are.same({ { key = "a.lua", value = "c.lua" } }, report[1].entries)
+    assert.are.same({ "a.lua" }, store:get("c.lua"))
+
+    core.collect_garbage(root, { scopes = { "personal" } })
+    assert.is_nil(store:get("c.lua"))
+    assert.are.same({ "b.lua", "rule://**/*.md" }, store:get("a.lua"))
+    assert.are.same({}, store:get(groups.key("empty")))
+  end)
 end)
 
 describe("Project module", function()
@@ -58,11 +199,620 @@ describe("Project module", function()
     assert.is_function(project.get_files)
     assert.is_function(project.get_open_buffer_paths)
   end)
+
+  it("should not look up renames without missing files", function()
+    assert.is_function(core.rename_file)
+    assert.are.same({}, project.detect_renames(vim.fn.getcwd(), {}))
+  end)
+
+  it("should rewrite the entries of files renamed in git", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root, "p")
+    vim.fn.writefile({ "local b = 1" }, root .. "/b.lua")
+    vim.fn.writefile({ "" }, root .. "/a.lua")
+    root = utils.canonical_path(root)
+    local git = { "git", "-C", root, "-c", "user.name=test", "-c", "user.email=test@example.com" }
+    vim.fn.system(vim.list_extend(vim.deepcopy(git), { "init", "-q" }))
+    vim.fn.system(vim.list_extend(vim.deepcopy(git), { "add", "." }))
+    vim.fn.system(vim.list_extend(vim.deepcopy(git), { "commit", "-qm", "init" }))
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/a.lua"))
+    assert.is_true(core.add_context_file(root .. "/b.lua"))
+
+    vim.fn.system(vim.list_extend(vim.deepcopy(git), { "mv", "b.lua", "c.lua" }))
+    assert.is_true(core.repair_group_renames())
+    assert.are.same({ "c.lua" }, storage.get_storage(root):get("a.lua"))
+    vim.cmd("bwipeout!")
+  end)
 end)
 
 describe("Storage module", function()
   it("should expose storage functionality", function()
     assert.is_function(storage.get_storage)
     assert.is_table(storage.Storage)
+    assert.is_function(storage.list_projects)
+  end)
+
+  it("should use a separate storage file per project", function()
+    local a = storage.get_storage("/tmp/project-a")
+    local b = storage.get_storage("/tmp/project-b")
+    assert.are_not.equal(a.path, b.path)
+    assert.are.equal(a, storage.get_storage("/tmp/project-a"))
+  end)
+
+  it("should store the absolute paths of older files relative to the project root", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/src", "p")
+    vim.fn.writefile({ "" }, root .. "/src/a.lua")
+    vim.fn.writefile({ "" }, root .. "/src/b.lua")
+    root = utils.canonical_path(root)
+    local path = storage.peek_storage(root, "personal").path
+    vim.fn.mkdir(vim.fn.fnamemodify(path, ":h"), "p")
+    vim.fn.writefile({ vim.fn.json_encode({ [root .. "/src/a.lua"] = { root .. "/src/b.lua", root } }) }, path)
+
+    local store = storage.get_storage(root)
+    assert.are.same({ "src/b.lua", "." }, store:get("src/a.lua"))
+    assert.is_nil(store:get(root .. "/src/a.lua"))
+    assert.are.equal(root, vim.fn.json_decode(table.concat(vim.fn.readfile(path), "\n")).root)
+  end)
+
+  it("should merge concurrent edits of the same group", function()
+    local path = vim.fn.tempname() .. ".json"
+    local ours = storage.Storage.open(path)
+    ours:set("a.lua", { "b.lua", "c.lua" })
+    local theirs = storage.Storage.open(path)
+
+    -- One instance adds an entry while the other removes one it loaded before the addition
+    assert.is_true(ours:set("a.lua", { "b.lua", "c.lua", "d.lua" }))
+    assert.is_true(theirs:set("a.lua", { "c.lua" }))
+    assert.are.same({ "c.lua", "d.lua" }, storage.Storage.open(path):get("a.lua"))
+  end)
+
+  it("should leave a corrupt file alone until it is repaired", function()
+    local path = vim.fn.tempname() .. ".json"
+    vim.fn.writefile({ "{ not json" }, path)
+    local store = storage.Storage.open(path)
+
+    assert.is_true(store.readonly)
+    assert.is_false(store:set("a.lua", { "b.lua" }))
+    assert.is_false(store:set("c.lua", { "d.lua" }))
+    assert.are.same({ "{ not json" }, vim.fn.readfile(path))
+    assert.are.equal(1, #vim.fn.glob(path .. ".corrupt-*", false, true))
+  end)
+
+  it("should not touch other projects' files when looking for a moved project", function()
+    local other = config.get().storage_path .. "/projects/" .. vim.fn.fnamemodify(vim.fn.tempname(), ":t") .. ".json"
+    vim.fn.mkdir(vim.fn.fnamemodify(other, ":h"), "p")
+    vim.fn.writefile({ "{ not json" }, other)
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root, "p")
+
+    storage.get_storage(root)
+    local backups = vim.fn.glob(other .. ".corrupt-*", true, true)
+    os.remove(other)
+    assert.are.same({}, backups)
+  end)
+
+  it("should keep copies of a corrupt shared file out of the project", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root, "p")
+    vim.fn.writefile({ "{ not json" }, root .. "/.context-groups.json")
+    root = utils.canonical_path(root)
+
+    assert.is_true(storage.get_shared_storage(root).corrupt)
+    assert.are.same({}, vim.fn.glob(root .. "/.context-groups.json.corrupt-*", true, true))
+    local backups = config.get().storage_path .. "/backups/*/.context-groups.json.corrupt-*"
+    assert.is_true(#vim.fn.glob(backups, true, true) > 0)
+  end)
+
+  it("should fall back to the default branch's groups", function()
+    local dir = vim.fn.tempname()
+    local base = storage.Storage.open(dir .. "/base.json")
+    local branch = storage.Storage.open(dir .. "/branch.json")
+    branch.fallback = base
+
+    base:set("a.lua", { "b.lua" })
+    assert.are.same({ "b.lua" }, branch:get("a.lua"))
+
+    branch:delete("a.lua")
+    assert.is_nil(branch:get("a.lua"))
+    assert.is_nil(branch:all()["a.lua"])
+    assert.are.same({ "b.lua" }, base:get("a.lua"))
+  end)
+end)
+
+describe("Rules", function()
+  it("should keep files that look like globs apart from rules", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.mkdir(root .. "/pages", "p")
+    for _, file in ipairs({ "pages/index.tsx", "pages/[id].tsx", "pages/i.tsx", "pages/d.css" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    root = utils.canonical_path(root)
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/pages/index.tsx"))
+
+    assert.is_true(core.add_context_file(root .. "/pages/[id].tsx"))
+    assert.is_true(core.add_context_rule("pages/*"))
+    assert.is_true(core.add_context_rule("!**/*.css"))
+    local paths = vim.tbl_map(function(entry)
+      return entry.path
+    end, core.get_context_entries())
+    table.sort(paths)
+    assert.are.same({ root .. "/pages/[id].tsx", root .. "/pages/i.tsx", root .. "/pages/index.tsx" }, paths)
+    assert.is_false(core.is_context_rule("pages/[id].tsx"))
+    vim.cmd("bwipeout!")
+  end)
+
+  it("should mark the rules of older storage files", function()
+    local path = vim.fn.tempname() .. ".json"
+    local group = { "src/*.go", { entry = "!x_test.go" }, "b.go", "@g" }
+    vim.fn.writefile({ vim.fn.json_encode({ version = 1, groups = { ["a.go"] = group } }) }, path)
+    local store = storage.Storage.open(path)
+    assert.are.same({ "rule://src/*.go", { entry = "rule://!x_test.go" }, "b.go", groups.key("g") }, store:get("a.go"))
+  end)
+
+  it("should keep the paths of unversioned storage files as files", function()
+    local path = vim.fn.tempname() .. ".json"
+    vim.fn.writefile({ vim.fn.json_encode({ ["pages/a.tsx"] = { "pages/[id].tsx", "src/" } }) }, path)
+    local store = storage.Storage.open(path)
+    assert.are.same({ "pages/[id].tsx", "src/" }, store:get("pages/a.tsx"))
+  end)
+end)
+
+describe("Groups module", function()
+  it("should keep named group keys apart from file keys", function()
+    assert.are.equal("auth-refactor", groups.name_from_key(groups.key("auth-refactor")))
+    assert.is_true(groups.is_named_key(groups.key("auth-refactor")))
+    assert.is_false(groups.is_named_key("src/auth.lua"))
+    assert.is_false(groups.is_named_key("@types/node"))
+    assert.are.equal("@auth-refactor", groups.label("auth-refactor"))
+  end)
+
+  it("should move named groups of older storage files to the group prefix", function()
+    local path = vim.fn.tempname() .. ".json"
+    local decoded = { version = 2, groups = { ["@g"] = { "a.go" }, ["@types/node"] = { "@g", "@scope/x.ts" } } }
+    vim.fn.writefile({ vim.fn.json_encode(decoded) }, path)
+    local store = storage.Storage.open(path)
+
+    assert.are.same({ "a.go" }, store:get(groups.key("g")))
+    assert.are.same({ groups.key("g"), "@scope/x.ts" }, store:get("@types/node"))
+    assert.is_nil(store:get("@g"))
+  end)
+
+  it("should include groups transitively, skipping cycles and applying exclusions", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    for _, file in ipairs({ "main.lua", "a.lua", "b1.lua", "b2.lua" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    root = utils.canonical_path(root)
+    -- Stored directly: adding the second reference would be refused because the groups include each other
+    local store = storage.get_storage(root)
+    store:set(groups.key("a"), { groups.key("b"), "b1.lua", "a.lua", "rule://!b2.lua" })
+    store:set(groups.key("b"), { groups.key("a"), "b1.lua", "b2.lua" })
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/main.lua"))
+
+    local found = vim.tbl_map(function(entry)
+      return { path = entry.path, group = entry.group }
+    end, core.get_context_entries(nil, { group = "a" }))
+    assert.are.same({ { path = root .. "/b1.lua", group = "b" }, { path = root .. "/a.lua" } }, found)
+    vim.cmd("bwipeout!")
+  end)
+
+  it("should validate group names", function()
+    assert.is_true(groups.is_valid_name("auth-refactor"))
+    assert.is_false(groups.is_valid_name(""))
+    assert.is_false(groups.is_valid_name("with space"))
+    assert.is_false(groups.is_valid_name("nested/name"))
+  end)
+end)
+
+describe("Entries module", function()
+  it("should round-trip range and symbol entries", function()
+    local range = entries.parse("src/server.go:120-180")
+    assert.are.equal("src/server.go", range.file)
+    assert.are.same({ 120, 180 }, range.range)
+    assert.are.equal("src/server.go#Server.Start", entries.format(entries.parse("src/server.go#Server.Start")))
+    assert.are.same({ file = "src/server.go" }, entries.parse("src/server.go"))
+  end)
+
+  it("should read existing files with # in their name as files", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root, "p")
+    vim.fn.writefile({ "" }, root .. "/notes#1.md")
+
+    assert.are.same({ file = "notes#1.md" }, entries.parse("notes#1.md", root))
+    assert.are.same({ file = "notes", symbol = "2.md" }, entries.parse("notes#2.md", root))
+  end)
+
+  it("should store entries without metadata as plain strings", function()
+    assert.are.equal("src/a.go", entries.make("src/a.go", {}))
+    local item = entries.make("src/a.go", { note = "legacy" })
+    assert.are.equal("src/a.go", entries.spec(item))
+    assert.are.equal("legacy", entries.note(item))
+  end)
+end)
+
+describe("Bundles module", function()
+  it("should round-trip groups through JSON and YAML bundles", function()
+    local groups_by_key = {
+      ["src/a.go"] = { "src/b.go", { entry = "src/c.go:10-20", note = 'the "router"', priority = 2 } },
+      [groups.key("auth")] = {},
+    }
+    for _, extension in ipairs({ "json", "yaml" }) do
+      local path = vim.fn.tempname() .. "." .. extension
+      assert.is_true(bundles.write(path, groups_by_key))
+      assert.are.same(groups_by_key, bundles.read(path).groups)
+    end
+  end)
+
+  it("should reject absolute paths", function()
+    local path = vim.fn.tempname() .. ".json"
+    bundles.write(path, { ["/etc/hosts"] = {} })
+    assert.is_nil(bundles.read(path))
+  end)
+
+  it("should reject paths that leave the project", function()
+    local path = vim.fn.tempname() .. ".json"
+    for _, groups_by_key in ipairs({
+      { ["../secrets.txt"] = {} },
+      { ["src/a.go"] = { "src/../../etc/passwd" } },
+      { ["src/a.go"] = { { entry = "..:1-5" } } },
+      { ["src/a.go"] = { "rule://!../**/*.env" } },
+    }) do
+      bundles.write(path, groups_by_key)
+      assert.is_nil(bundles.read(path))
+    end
+
+    bundles.write(path, { ["src/a..b.go"] = { "src/..hidden/c.go" } })
+    assert.is_table(bundles.read(path))
+  end)
+
+  it("should reject bundles without a version", function()
+    local path = vim.fn.tempname() .. ".json"
+    vim.fn.writefile({ vim.fn.json_encode({ groups = { ["src/a.go"] = { "src/*.go" } } }) }, path)
+    assert.is_nil(bundles.read(path))
+  end)
+end)
+
+describe("LSP module", function()
+  it("should resolve imports to files, index files and package directories", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/pkg/util", "p")
+    vim.fn.writefile({ "" }, root .. "/pkg/models.py")
+    vim.fn.writefile({ "" }, root .. "/pkg/__init__.py")
+    root = utils.canonical_path(root)
+
+    assert.are.equal(root .. "/pkg/models.py", lsp.find_candidate({ root .. "/pkg/models" }, "py"))
+    assert.are.equal(root .. "/pkg/__init__.py", lsp.find_candidate({ root .. "/pkg" }, "py"))
+    assert.are.equal(root .. "/pkg/util", lsp.find_candidate({ root .. "/pkg/util" }, "go"))
+    assert.is_nil(lsp.find_candidate({ root .. "/missing" }, "py"))
+  end)
+end)
+
+describe("Go import handler", function()
+  it("should resolve module packages and local replacements to directories", function()
+    local go = require("context-groups.lsp.go")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/app/internal/db", "p")
+    vim.fn.mkdir(root .. "/shared/log", "p")
+    vim.fn.writefile({
+      "module example.com/app",
+      "",
+      "require (",
+      "\texample.com/shared v0.1.0 // indirect",
+      ")",
+      "",
+      "replace example.com/shared => ../shared",
+    }, root .. "/app/go.mod")
+    root = utils.canonical_path(root)
+    local main = root .. "/app/main.go"
+
+    assert.are.equal(root .. "/app/internal/db", go.resolve_import("example.com/app/internal/db", main))
+    assert.are.equal(root .. "/shared/log", go.resolve_import("example.com/shared/log", main))
+    assert.is_nil(go.resolve_import("example.com/app/missing", main))
+    assert.is_true(go.is_stdlib("net/http", nil))
+    assert.is_false(go.is_stdlib("github.com/pkg/errors", nil))
+  end)
+
+  it("should find required modules in GOMODCACHE", function()
+    local go = require("context-groups.lsp.go")
+    local root = vim.fn.tempname()
+    local modcache = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/app", "p")
+    vim.fn.mkdir(root .. "/other/x", "p")
+    vim.fn.mkdir(modcache .. "/example.com/!lib@v1.0.0/pkg", "p")
+    vim.fn.mkdir(modcache .. "/example.com/other@v0.1.0/x", "p")
+    vim.fn.writefile({
+      "module example.com/app",
+      "",
+      "replace example.com/other v0.2.0 => ../other",
+      "",
+      "require (",
+      "\texample.com/Lib v1.0.0",
+      "\texample.com/other v0.1.0",
+      ")",
+    }, root .. "/app/go.mod")
+    root = utils.canonical_path(root)
+    modcache = utils.canonical_path(modcache)
+    local main = root .. "/app/main.go"
+
+    local previous = vim.env.GOMODCACHE
+    vim.env.GOMODCACHE = modcache
+    local lib = go.resolve_import("example.com/Lib/pkg", main)
+    local other = go.resolve_import("example.com/other/x", main)
+    vim.env.GOMODCACHE = previous
+
+    assert.are.equal(modcache .. "/example.com/!lib@v1.0.0/pkg", lib)
+    -- The replacement names another version than the required one
+    assert.are.equal(modcache .. "/example.com/other@v0.1.0/x", other)
+  end)
+
+  it("should leave tests and other platforms' files out of a package's files", function()
+    local go = require("context-groups.lsp.go")
+    local dir = vim.fn.tempname()
+    vim.fn.mkdir(dir, "p")
+    for _, file in ipairs({ "db.go", "db_test.go", "poll_plan9.go", "query.go" }) do
+      vim.fn.writefile({ "package db" }, dir .. "/" .. file)
+    end
+    dir = utils.canonical_path(dir)
+
+    assert.are.same({ dir .. "/db.go", dir .. "/query.go" }, go.package_files(dir))
+  end)
+end)
+
+describe("Python import handler", function()
+  it("should resolve absolute, attribute and relative imports to project modules", function()
+    local python = require("context-groups.lsp.python")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.mkdir(root .. "/src/pkg/sub", "p")
+    for _, file in ipairs({ "__init__.py", "models.py", "sub/__init__.py", "sub/views.py" }) do
+      vim.fn.writefile({ "" }, root .. "/src/pkg/" .. file)
+    end
+    root = utils.canonical_path(root)
+    local views = root .. "/src/pkg/sub/views.py"
+
+    assert.are.equal(root .. "/src/pkg/models.py", python.resolve_import("pkg.models", views))
+    assert.are.equal(root .. "/src/pkg/models.py", python.resolve_import("pkg.models.User", views))
+    assert.are.equal(root .. "/src/pkg/models.py", python.resolve_import("..models", views))
+    assert.are.equal(root .. "/src/pkg/sub/__init__.py", python.resolve_import(".", views))
+    assert.is_false(python.is_external("pkg.models", root .. "/src/pkg/models.py", views))
+  end)
+end)
+
+describe("Rust import handler", function()
+  it("should resolve module declarations and relative use paths", function()
+    local rust = require("context-groups.lsp.rust")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/src/net", "p")
+    for _, file in ipairs({ "lib.rs", "net.rs", "net/http.rs" }) do
+      vim.fn.writefile({ "" }, root .. "/src/" .. file)
+    end
+    root = utils.canonical_path(root)
+
+    local http = root .. "/src/net/http.rs"
+
+    assert.are.equal(root .. "/src/net.rs", rust.resolve_import("net", root .. "/src/lib.rs"))
+    assert.are.equal(http, rust.resolve_import("self::http::{Client, Request}", root .. "/src/net.rs"))
+    assert.are.equal(http, rust.resolve_import("super::http::Client", root .. "/src/net/tls.rs"))
+    assert.are.equal(root .. "/src/net.rs", rust.resolve_import("super::super::net", http))
+    assert.is_true(rust.is_stdlib("std::io::{self, Read}"))
+  end)
+
+  it("should resolve every leaf of a use tree", function()
+    local rust = require("context-groups.lsp.rust")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/src/net", "p")
+    for _, file in ipairs({ "lib.rs", "net.rs", "net/http.rs" }) do
+      vim.fn.writefile({ "" }, root .. "/src/" .. file)
+    end
+    root = utils.canonical_path(root)
+
+    assert.are.same({ "crate::a", "crate::b::C" }, rust.expand_import("crate::{a, b::C}"))
+    assert.are.same({ "std::io", "std::io::Read", "std::x" }, rust.expand_import("std::{io::{self, Read}, x as y}"))
+    local paths = vim.tbl_map(function(path)
+      return rust.resolve_import(path, root .. "/src/lib.rs")
+    end, rust.expand_import("self::{net, net::http::Client}"))
+    assert.are.same({ root .. "/src/net.rs", root .. "/src/net/http.rs" }, paths)
+  end)
+end)
+
+describe("TypeScript import handler", function()
+  it("should resolve tsconfig paths, output extensions and workspace packages", function()
+    local typescript = require("context-groups.lsp.typescript")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/src/foo", "p")
+    vim.fn.mkdir(root .. "/packages/ui/src", "p")
+    vim.fn.writefile({ '{ "workspaces": ["packages/*"] }' }, root .. "/package.json")
+    vim.fn.writefile({
+      "{",
+      "  // Aliases of the app",
+      '  "compilerOptions": { "baseUrl": ".", "paths": { "@app/*": ["src/*"], }, },',
+      "}",
+    }, root .. "/tsconfig.json")
+    vim.fn.writefile(
+      { '{ "name": "@org/ui", "exports": { ".": { "import": "./src/index.ts" } } }' },
+      root .. "/packages/ui/package.json"
+    )
+    vim.fn.mkdir(root .. "/util", "p")
+    for _, file in ipairs({ "src/foo/index.ts", "src/bar.ts", "packages/ui/src/index.ts", "util/format.ts" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    root = utils.canonical_path(root)
+    local main = root .. "/src/main.ts"
+
+    assert.are.equal(root .. "/src/foo/index.ts", typescript.resolve_import("@app/foo", main))
+    assert.are.equal(root .. "/src/bar.ts", typescript.resolve_import("./bar.js", main))
+    assert.are.equal(root .. "/packages/ui/src/index.ts", typescript.resolve_import("@org/ui", main))
+    assert.is_true(typescript.is_stdlib("node:fs"))
+
+    -- Built-in names only win when no alias or baseUrl file matches
+    local format = typescript.resolve_import("util/format", main)
+    assert.are.equal(root .. "/util/format.ts", format)
+    assert.is_false(typescript.is_stdlib("util/format", format))
+    assert.is_nil(typescript.resolve_import("fs/promises", main))
+    assert.is_true(typescript.is_stdlib("fs/promises"))
+  end)
+end)
+
+describe("Lua import handler", function()
+  it("should resolve requires to the project's lua directory", function()
+    local lua = require("context-groups.lsp.lua")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.mkdir(root .. "/lua/plugin/ui", "p")
+    vim.fn.writefile({ "" }, root .. "/lua/plugin/core.lua")
+    vim.fn.writefile({ "" }, root .. "/lua/plugin/ui/init.lua")
+    root = utils.canonical_path(root)
+    local init = root .. "/lua/plugin/init.lua"
+
+    assert.are.equal(root .. "/lua/plugin/core.lua", lua.resolve_import("plugin.core", init))
+    assert.are.equal(root .. "/lua/plugin/ui/init.lua", lua.resolve_import("plugin.ui", init))
+    assert.is_true(lua.is_stdlib("vim.lsp", nil))
+  end)
+end)
+
+describe("C/C++ import handler", function()
+  it("should resolve includes through compile_commands.json with their source files", function()
+    local c = require("context-groups.lsp.c")
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.mkdir(root .. "/include/net", "p")
+    vim.fn.mkdir(root .. "/src/net", "p")
+    vim.fn.mkdir(root .. "/build", "p")
+    for _, file in ipairs({ "include/net/socket.h", "src/net/socket.cc", "src/main.cc" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+    vim.fn.writefile({
+      vim.fn.json_encode({ { directory = root, file = "src/main.cc", command = "c++ -I include -c src/main.cc" } }),
+    }, root .. "/build/compile_commands.json")
+    root = utils.canonical_path(root)
+    local main = root .. "/src/main.cc"
+
+    assert.are.equal(root .. "/include/net/socket.h", c.resolve_import("net/socket.h", main))
+    assert.are.equal(root .. "/include/net/socket.h", c.resolve_import("<net/socket.h>", main))
+    assert.are.same({ root .. "/src/net/socket.cc" }, c.get_related(root .. "/include/net/socket.h", main))
+    assert.is_true(c.is_stdlib("<vector>", nil, main))
+  end)
+end)
+
+describe("Graph module", function()
+  it("should follow imports up to the depth limit", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.writefile({ 'local b = require("b")' }, root .. "/a.lua")
+    vim.fn.writefile({ 'local c = require("c")' }, root .. "/b.lua")
+    vim.fn.writefile({ 'local a = require("a")' }, root .. "/c.lua")
+    root = utils.canonical_path(root)
+
+    local nodes = graph.closure(root .. "/a.lua", { depth = 1 })
+    assert.are.same({ { path = root .. "/b.lua", depth = 1, imported_by = root .. "/a.lua", import = "b" } }, nodes)
+
+    nodes = graph.closure(root .. "/a.lua", { depth = 5 })
+    assert.are.equal(2, #nodes)
+    assert.are.equal(root .. "/b.lua", nodes[2].imported_by)
+  end)
+
+  it("should add dependencies nearest first", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.writefile({ 'local z = require("z")' }, root .. "/a.lua")
+    vim.fn.writefile({ 'local b = require("b")' }, root .. "/z.lua")
+    vim.fn.writefile({ "" }, root .. "/b.lua")
+    root = utils.canonical_path(root)
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/a.lua"))
+
+    assert.is_true(core.add_dependencies(nil, { depth = 2, files = { root .. "/b.lua", root .. "/z.lua" } }))
+    vim.cmd("bwipeout!")
+    assert.are.same({ "z.lua", "b.lua" }, storage.get_storage(root):get("a.lua"))
+  end)
+end)
+
+describe("Pairing module", function()
+  it("should pair tests and implementations both ways", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/pkg", "p")
+    vim.fn.mkdir(root .. "/tests/pkg", "p")
+    for _, file in ipairs({ "server.go", "server_test.go", "pkg/models.py", "tests/pkg/test_models.py" }) do
+      vim.fn.writefile({ "" }, root .. "/" .. file)
+    end
+
+    assert.are.same({ root .. "/server.go" }, pairing.find(root .. "/server_test.go", root))
+    assert.are.same({ root .. "/server_test.go" }, pairing.find(root .. "/server.go", root))
+    assert.are.same({ root .. "/pkg/models.py" }, pairing.find(root .. "/tests/pkg/test_models.py", root))
+    assert.are.same({ root .. "/tests/pkg/test_models.py" }, pairing.find(root .. "/pkg/models.py", root))
+  end)
+end)
+
+describe("History module", function()
+  it("should keep a bounded list of edits", function()
+    local root = "/history-test"
+    for i = 1, 105 do
+      history.record(root, "Add " .. i, { { scope = "personal", key = "a.lua", before = {}, after = { "b.lua" } } })
+    end
+
+    local entries_list, position = history.list(root)
+    assert.are.equal(100, #entries_list)
+    assert.are.equal(100, position)
+    assert.are.equal("Add 105", entries_list[#entries_list].label)
+  end)
+
+  it("should undo into the branch that was edited", function()
+    local root = utils.canonical_path(vim.fn.tempname())
+    local branch = storage.get_branch_storage(root, "feature")
+    branch:set("a.lua", { "b.lua" })
+    history.record(root, "Add b.lua", {
+      { scope = "personal", branch = "feature", key = "a.lua", before = nil, after = { "b.lua" } },
+    })
+    storage.get_storage(root):set("a.lua", { "c.lua" })
+
+    local entry, success = history.undo(root)
+    assert.are.equal("Add b.lua", entry.label)
+    assert.is_true(success)
+    assert.is_nil(branch:get("a.lua"))
+    assert.are.same({ "c.lua" }, storage.get_storage(root):get("a.lua"))
+    assert.are.equal(0, select(2, history.list(root)))
+  end)
+
+  it("should undo deleting a named group and skip clearing an empty one", function()
+    local root = vim.fn.tempname()
+    vim.fn.mkdir(root .. "/.git", "p")
+    vim.fn.writefile({ "" }, root .. "/main.lua")
+    root = utils.canonical_path(root)
+    vim.cmd.edit(vim.fn.fnameescape(root .. "/main.lua"))
+    assert.is_true(groups.create(root, "docs", { scope = "personal" }))
+    assert.is_nil(core.clear_context_group(nil, { group = "docs" }))
+    assert.is_true(groups.delete(root, "docs"))
+    vim.cmd("bwipeout!")
+
+    local labels = vim.tbl_map(function(entry)
+      return entry.label
+    end, (history.list(root)))
+    assert.are.same({ "Create @docs", "Delete @docs" }, labels)
+    assert.is_true(select(2, history.undo(root)))
+    assert.is_true(groups.exists(root, "docs"))
+  end)
+end)
+
+describe("Utils module", function()
+  it("should encode pretty JSON with sorted keys", function()
+    local encoded = utils.json_encode_pretty({ b = { "x" }, a = 1 })
+    assert.are.equal('{\n  "a": 1,\n  "b": [\n    "x"\n  ]\n}', encoded)
+  end)
+
+  it("should match globs across directories", function()
+    assert.is_true(utils.glob_match("src/**/*.go", "src/api/v1/handler.go"))
+    assert.is_true(utils.glob_match("src/**/*.go", "src/main.go"))
+    assert.is_false(utils.glob_match("src/*.go", "src/api/handler.go"))
+    assert.is_true(utils.glob_match("**/*_test.go", "src/a_test.go"))
+    assert.is_false(utils.glob_match("**/*_test.go", "src/api/handler.go"))
+    assert.is_false(utils.glob_match("*_spec.lua", "a spec.lua"))
+  end)
+
+  it("should match globs under roots with punctuation", function()
+    assert.is_true(utils.glob_match("/home/jane_doe/my-app (1)/src/**/*.go", "/home/jane_doe/my-app (1)/src/a.go"))
+    assert.is_true(utils.glob_match("/srv/100%/*.[ch]", "/srv/100%/main.c"))
+    assert.is_false(utils.glob_match("/home/jane_doe/src/*.go", "/home/janeXdoe/src/a.go"))
   end)
 end)